@@ -0,0 +1,133 @@
+use candid::{CandidType, Nat, Principal};
+use icrc_ledger_types::icrc1::{account::Account, transfer::Memo};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api_error::ApiError,
+    boosted::Boost,
+    helpers::ledger::{validate_transfer, IcrcLedger, TransactionLedger},
+    subject::{Subject, SubjectType},
+    CanisterResult,
+};
+
+/// The boost time bought with `amount` tokens, partial amounts are converted proportionally
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct BoostPrice {
+    pub amount: u64,
+    pub seconds: u64,
+}
+
+impl BoostPrice {
+    pub fn seconds_for(&self, amount: &Nat) -> u64 {
+        if self.amount == 0 {
+            return 0;
+        }
+
+        let amount = u128::try_from(&amount.0).unwrap_or(u128::MAX);
+        let seconds = amount.saturating_mul(self.seconds as u128) / self.amount as u128;
+        u64::try_from(seconds).unwrap_or(u64::MAX)
+    }
+}
+
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct BoostPaymentConfig {
+    pub ledger: Principal,
+    pub treasury: Account,
+    pub price: BoostPrice,
+}
+
+/// The memo the boost transfer has to carry, it ties the payment to the boosted group or event
+pub fn boost_memo(subject: &Subject) -> Memo {
+    Memo::from(
+        format!(
+            "boost:{}:{}",
+            memo_subject_type(&subject.get_type()),
+            subject.get_id()
+        )
+        .into_bytes(),
+    )
+}
+
+// wallets build the memo themselves, so these names must not change with the enum
+fn memo_subject_type(subject_type: &SubjectType) -> &'static str {
+    match subject_type {
+        SubjectType::None => "none",
+        SubjectType::Group => "group",
+        SubjectType::Event => "event",
+        SubjectType::Profile => "profile",
+        SubjectType::Member => "member",
+        SubjectType::Attendee => "attendee",
+    }
+}
+
+pub async fn verify_boost_payment(
+    config: &BoostPaymentConfig,
+    subject: &Subject,
+    blockheight: u64,
+    sender: Principal,
+    boosts: &[Boost],
+) -> CanisterResult<u64> {
+    verify_boost_payment_with(&IcrcLedger, config, subject, blockheight, sender, boosts).await
+}
+
+/// Verifies that the block is a transfer of at least the boost price from the sender to the treasury with the
+/// `boost_memo` of the subject, and returns the bought boost seconds. `boosts` are the boosts already recorded
+/// for the block height, usually fetched with `BoostedFilter::Blockheight`.
+pub async fn verify_boost_payment_with<L: TransactionLedger>(
+    ledger: &L,
+    config: &BoostPaymentConfig,
+    subject: &Subject,
+    blockheight: u64,
+    sender: Principal,
+    boosts: &[Boost],
+) -> CanisterResult<u64> {
+    if boosts.iter().any(|boost| boost.blockheight == blockheight) {
+        return Err(ApiError::duplicate()
+            .add_method_name("verify_boost_payment")
+            .add_message("Blockheight is already used for a boost")
+            .add_info(blockheight));
+    }
+
+    let transfer = ledger
+        .get_transfer(config.ledger, blockheight)
+        .await?
+        .ok_or_else(|| {
+            ApiError::not_found()
+                .add_method_name("verify_boost_payment")
+                .add_message("Transaction not found")
+                .add_info(blockheight)
+        })?;
+
+    validate_transfer(&transfer, sender, &config.treasury, &boost_memo(subject)).map_err(
+        |err| {
+            err.add_method_name("verify_boost_payment")
+                .add_info(blockheight)
+        },
+    )?;
+
+    if config.price.amount == 0 || transfer.amount < config.price.amount {
+        return Err(ApiError::bad_request()
+            .add_method_name("verify_boost_payment")
+            .add_message("Transferred amount is below the boost price")
+            .add_info(&transfer.amount));
+    }
+
+    Ok(config.price.seconds_for(&transfer.amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boost_memo_uses_stable_subject_names() {
+        assert_eq!(
+            boost_memo(&Subject::Group(7)).0.as_slice(),
+            b"boost:group:7"
+        );
+        assert_eq!(
+            boost_memo(&Subject::Event(12)).0.as_slice(),
+            b"boost:event:12"
+        );
+    }
+}
@@ -0,0 +1,169 @@
+pub const EARTH_RADIUS_KM: f64 = 6371.0088;
+
+pub const GEOHASH_MAX_PRECISION: usize = 12;
+
+const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Great-circle distance in kilometers between two coordinates using the haversine formula
+pub fn haversine_km(lat_a: f64, lng_a: f64, lat_b: f64, lng_b: f64) -> f64 {
+    let d_lat = (lat_b - lat_a).to_radians();
+    let d_lng = (lng_b - lng_a).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat_a.to_radians().cos() * lat_b.to_radians().cos() * (d_lng / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+}
+
+pub fn is_valid_coordinate(lat: f64, lng: f64) -> bool {
+    lat.is_finite()
+        && lng.is_finite()
+        && (-90.0..=90.0).contains(&lat)
+        && (-180.0..=180.0).contains(&lng)
+}
+
+pub fn geohash_encode(lat: f64, lng: f64, precision: usize) -> String {
+    let precision = precision.clamp(1, GEOHASH_MAX_PRECISION);
+    let lat = lat.clamp(-90.0, 90.0);
+    let lng = wrap_longitude(lng);
+
+    let mut lat_range = (-90.0, 90.0);
+    let mut lng_range = (-180.0, 180.0);
+
+    let mut hash = String::with_capacity(precision);
+    let mut is_lng_bit = true;
+    let mut bit = 0;
+    let mut index = 0;
+
+    while hash.len() < precision {
+        let (range, value) = match is_lng_bit {
+            true => (&mut lng_range, lng),
+            false => (&mut lat_range, lat),
+        };
+
+        let mid = (range.0 + range.1) / 2.0;
+        if value >= mid {
+            index = (index << 1) | 1;
+            range.0 = mid;
+        } else {
+            index <<= 1;
+            range.1 = mid;
+        }
+
+        is_lng_bit = !is_lng_bit;
+        bit += 1;
+
+        if bit == 5 {
+            hash.push(GEOHASH_ALPHABET[index] as char);
+            bit = 0;
+            index = 0;
+        }
+    }
+
+    hash
+}
+
+/// Returns the center `(lat, lng)` of the geohash cell, or `None` if the hash contains invalid characters
+pub fn geohash_decode(hash: &str) -> Option<(f64, f64)> {
+    let mut lat_range = (-90.0, 90.0);
+    let mut lng_range = (-180.0, 180.0);
+    let mut is_lng_bit = true;
+
+    for c in hash.bytes() {
+        let index = GEOHASH_ALPHABET.iter().position(|a| *a == c)?;
+        for shift in (0..5).rev() {
+            let range = match is_lng_bit {
+                true => &mut lng_range,
+                false => &mut lat_range,
+            };
+
+            let mid = (range.0 + range.1) / 2.0;
+            if (index >> shift) & 1 == 1 {
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            is_lng_bit = !is_lng_bit;
+        }
+    }
+
+    Some((
+        (lat_range.0 + lat_range.1) / 2.0,
+        (lng_range.0 + lng_range.1) / 2.0,
+    ))
+}
+
+/// Size of a geohash cell in degrees as `(lat_degrees, lng_degrees)`
+pub fn geohash_cell_size(precision: usize) -> (f64, f64) {
+    let bits = precision.clamp(1, GEOHASH_MAX_PRECISION) as i32 * 5;
+    let lng_bits = (bits + 1) / 2;
+    let lat_bits = bits / 2;
+
+    (180.0 / 2f64.powi(lat_bits), 360.0 / 2f64.powi(lng_bits))
+}
+
+/// The highest precision whose cells are still at least `radius_km` wide and high at the given latitude,
+/// so a radius search never has to look further than the neighbouring cells
+pub fn geohash_precision_for_radius(radius_km: f64, lat: f64) -> usize {
+    let km_per_lat_degree = EARTH_RADIUS_KM.to_radians();
+    let km_per_lng_degree = km_per_lat_degree * lat.clamp(-89.0, 89.0).to_radians().cos();
+
+    (1..=GEOHASH_MAX_PRECISION)
+        .rev()
+        .find(|precision| {
+            let (lat_degrees, lng_degrees) = geohash_cell_size(*precision);
+            lat_degrees * km_per_lat_degree >= radius_km
+                && lng_degrees * km_per_lng_degree >= radius_km
+        })
+        .unwrap_or(1)
+}
+
+/// The geohash cells of the given precision that cover the area between the given corners
+pub fn geohash_cells_between(
+    min_lat: f64,
+    min_lng: f64,
+    max_lat: f64,
+    max_lng: f64,
+    precision: usize,
+) -> Vec<String> {
+    let (lat_step, lng_step) = geohash_cell_size(precision);
+    let min_lat = min_lat.clamp(-90.0, 90.0);
+    let max_lat = max_lat.clamp(-90.0, 90.0);
+
+    let lng_span = match max_lng >= min_lng {
+        true => max_lng - min_lng,
+        // the box crosses the antimeridian
+        false => max_lng + 360.0 - min_lng,
+    };
+
+    let mut cells: Vec<String> = vec![];
+    let mut lat = min_lat;
+    loop {
+        let mut lng_offset = 0.0;
+        loop {
+            let hash = geohash_encode(lat, min_lng + lng_offset, precision);
+            if !cells.contains(&hash) {
+                cells.push(hash);
+            }
+
+            if lng_offset >= lng_span {
+                break;
+            }
+            lng_offset = (lng_offset + lng_step).min(lng_span);
+        }
+
+        if lat >= max_lat {
+            break;
+        }
+        lat = (lat + lat_step).min(max_lat);
+    }
+
+    cells
+}
+
+pub fn wrap_longitude(lng: f64) -> f64 {
+    if (-180.0..=180.0).contains(&lng) {
+        return lng;
+    }
+    (lng + 180.0).rem_euclid(360.0) - 180.0
+}
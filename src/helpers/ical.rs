@@ -0,0 +1,1211 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use candid::{CandidType, Deserialize};
+
+use crate::{
+    api_error::ApiError,
+    asset::Asset,
+    date_range::DateRange,
+    event_with_attendees::{EventResponse, EventWithAttendees, PostEvent},
+    helpers::time_helper::{
+        civil_from_days, days_from_civil, days_in_month, days_to_nanoseconds, format_utc_offset,
+        parse_utc_offset, split_nanoseconds, weekday_from_days, NANOSECONDS_PER_DAY,
+        NANOSECONDS_PER_SECOND,
+    },
+    location::{Address, Location, PhysicalLocation},
+    privacy::PrivacyType,
+    recurrence::{Recurrence, RecurrenceFrequency, Weekday},
+    CanisterResult,
+};
+
+pub const ICAL_PRODUCT_ID: &str = "-//Catalyze//Events//EN";
+pub const ICAL_UID_DOMAIN: &str = "catalyze.one";
+
+// RFC 5545 lines should not be longer than 75 octets excluding the line break
+const ICAL_MAX_LINE_LENGTH: usize = 75;
+const ICAL_LINE_BREAK: &str = "\r\n";
+
+/// A single VEVENT, built from an event or event response and written by `to_ical_calendar`
+#[derive(Clone, Debug, Default)]
+pub struct ICalEvent {
+    pub uid: String,
+    pub summary: String,
+    pub description: String,
+    pub date: DateRange,
+    pub location: Option<String>,
+    pub geo: Option<(f64, f64)>,
+    pub url: Option<String>,
+    pub is_canceled: bool,
+    pub recurrence: Option<Recurrence>,
+    pub created_on: u64,
+    pub updated_on: u64,
+}
+
+impl ICalEvent {
+    /// One VEVENT per date, the recurrence is attached to the first date
+    pub fn from_event(id: u64, event: &EventWithAttendees) -> Vec<Self> {
+        let mut dates = event.dates.clone();
+        dates.sort_by_key(|date| date.start_date());
+
+        dates
+            .into_iter()
+            .enumerate()
+            .map(|(index, date)| {
+                let uid = match index {
+                    0 => format!("event-{id}@{ICAL_UID_DOMAIN}"),
+                    _ => format!("event-{id}-{index}@{ICAL_UID_DOMAIN}"),
+                };
+
+                let mut ical_event = Self {
+                    uid,
+                    summary: event.metadata.name.clone(),
+                    description: event.metadata.description.clone(),
+                    date,
+                    is_canceled: event.is_canceled.is_some(),
+                    recurrence: event.recurrence.clone().filter(|_| index == 0),
+                    created_on: event.created_on,
+                    updated_on: event.updated_on,
+                    ..Default::default()
+                };
+                ical_event.set_location(&event.metadata.location, &event.metadata.website);
+                ical_event
+            })
+            .collect()
+    }
+
+    pub fn from_response(event: &EventResponse) -> Self {
+        let mut ical_event = Self {
+            uid: format!("event-{}@{ICAL_UID_DOMAIN}", event.id),
+            summary: event.name.clone(),
+            description: event.description.clone(),
+            date: event.date.clone(),
+            is_canceled: event.is_canceled.0,
+            recurrence: event.recurrence.clone(),
+            created_on: event.created_on,
+            updated_on: event.updated_on,
+            ..Default::default()
+        };
+        ical_event.set_location(&event.location, &event.website);
+        ical_event
+    }
+
+    fn set_location(&mut self, location: &Location, website: &str) {
+        let digital = match location {
+            Location::Digital(url) => Some(url.clone()),
+            Location::MultiLocation(multi) => Some(multi.digital.clone()),
+            _ => None,
+        }
+        .filter(|url| !url.trim().is_empty());
+
+        let physical = location.physical();
+
+        self.location = physical
+            .map(|physical| format_address(&physical.address))
+            .filter(|address| !address.is_empty())
+            .or_else(|| digital.clone());
+        self.geo = physical
+            .filter(|physical| physical.has_coordinates())
+            .map(|physical| (physical.lat(), physical.lng()));
+        self.url =
+            digital.or_else(|| Some(website.to_string()).filter(|url| !url.trim().is_empty()));
+    }
+
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", escape_text(&self.uid)),
+            format!("DTSTAMP:{}", format_date_time(self.updated_on)),
+            format!("CREATED:{}", format_date_time(self.created_on)),
+            format!("LAST-MODIFIED:{}", format_date_time(self.updated_on)),
+            self.format_date_property("DTSTART", self.date.start_date()),
+            self.format_date_property("DTEND", self.date.end_date().max(self.date.start_date())),
+            format!("SUMMARY:{}", escape_text(&self.summary)),
+        ];
+
+        if !self.description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape_text(&self.description)));
+        }
+
+        if let Some(location) = &self.location {
+            lines.push(format!("LOCATION:{}", escape_text(location)));
+        }
+
+        if let Some((lat, lng)) = self.geo {
+            lines.push(format!("GEO:{lat:.6};{lng:.6}"));
+        }
+
+        if let Some(url) = &self.url {
+            lines.push(format!("URL:{url}"));
+        }
+
+        if let Some(recurrence) = &self.recurrence {
+            lines.push(format!("RRULE:{}", format_rrule(recurrence)));
+            if !recurrence.exceptions.is_empty() {
+                let exceptions: Vec<String> = recurrence
+                    .exceptions
+                    .iter()
+                    .map(|exception| self.format_date_property("EXDATE", *exception))
+                    .collect();
+                lines.extend(exceptions);
+            }
+        }
+
+        lines.push(match self.is_canceled {
+            true => "STATUS:CANCELLED".to_string(),
+            false => "STATUS:CONFIRMED".to_string(),
+        });
+        lines.push("END:VEVENT".to_string());
+        lines
+    }
+
+    /// The fixed offset timezone of the date, `UTC` and region based timezones are written as UTC
+    fn timezone(&self) -> Option<(&str, i64)> {
+        let timezone = self.date.timezone.as_deref()?;
+        parse_utc_offset(timezone)
+            .filter(|offset| *offset != 0)
+            .map(|offset| (timezone, offset))
+    }
+
+    fn format_date_property(&self, name: &str, timestamp: u64) -> String {
+        match self.timezone() {
+            Some((timezone, offset)) => format!(
+                "{name};TZID=\"{timezone}\":{}",
+                format_local_date_time(timestamp, offset)
+            ),
+            None => format!("{name}:{}", format_date_time(timestamp)),
+        }
+    }
+}
+
+/// A VTIMEZONE for a fixed offset timezone, referenced by the `TZID` of the event dates
+fn timezone_to_lines(timezone: &str, offset: i64) -> Vec<String> {
+    let offset = format_utc_offset(offset).replace(':', "");
+    vec![
+        "BEGIN:VTIMEZONE".to_string(),
+        format!("TZID:{timezone}"),
+        "BEGIN:STANDARD".to_string(),
+        "DTSTART:19700101T000000".to_string(),
+        format!("TZOFFSETFROM:{offset}"),
+        format!("TZOFFSETTO:{offset}"),
+        "END:STANDARD".to_string(),
+        "END:VTIMEZONE".to_string(),
+    ]
+}
+
+/// A VCALENDAR with all events, used to bundle the events of a group into one file
+pub fn to_ical_calendar(events: &[ICalEvent], name: Option<&str>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{ICAL_PRODUCT_ID}"),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+    ];
+
+    if let Some(name) = name {
+        lines.push(format!("X-WR-CALNAME:{}", escape_text(name)));
+    }
+
+    let timezones: BTreeSet<(&str, i64)> = events.iter().filter_map(ICalEvent::timezone).collect();
+    lines.extend(
+        timezones
+            .into_iter()
+            .flat_map(|(timezone, offset)| timezone_to_lines(timezone, offset)),
+    );
+    lines.extend(events.iter().flat_map(ICalEvent::to_lines));
+    lines.push("END:VCALENDAR".to_string());
+
+    lines
+        .iter()
+        .map(|line| fold_line(line))
+        .collect::<Vec<String>>()
+        .join(ICAL_LINE_BREAK)
+        + ICAL_LINE_BREAK
+}
+
+pub fn event_to_ical(id: u64, event: &EventWithAttendees) -> String {
+    to_ical_calendar(&ICalEvent::from_event(id, event), None)
+}
+
+pub fn events_to_ical(events: &[(u64, EventWithAttendees)], name: Option<&str>) -> String {
+    let ical_events: Vec<ICalEvent> = events
+        .iter()
+        .flat_map(|(id, event)| ICalEvent::from_event(*id, event))
+        .collect();
+    to_ical_calendar(&ical_events, name)
+}
+
+pub fn event_responses_to_ical(events: &[EventResponse], name: Option<&str>) -> String {
+    let ical_events: Vec<ICalEvent> = events.iter().map(ICalEvent::from_response).collect();
+    to_ical_calendar(&ical_events, name)
+}
+
+/// The result of `parse_ical`, events that can not be imported are skipped with the reason
+#[derive(Clone, Debug, Default, CandidType, Deserialize)]
+pub struct ICalImport {
+    pub events: Vec<PostEvent>,
+    pub skipped: Vec<ICalSkippedEvent>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub struct ICalSkippedEvent {
+    pub uid: Option<String>,
+    pub name: String,
+    pub reason: String,
+}
+
+/// Parses the VEVENTs of an `.ics` file into events for the given group, canceled events are left out.
+/// A `TZID` is resolved as fixed offset or with the VTIMEZONE of the file, times without a `TZID` or `Z` suffix
+/// are read as UTC. The timezone of the event is the UTC offset at its start, recurrences are expanded in that
+/// fixed offset so later occurrences do not follow daylight saving time changes.
+/// Events with an unsupported recurrence rule or timezone or a date before 1970 are skipped, only a malformed file
+/// fails the import.
+pub fn parse_ical(input: &str, group_id: Option<u64>) -> CanisterResult<ICalImport> {
+    let calendar = ICalComponent::parse(input)?;
+    let zones = ICalZones::from_calendar(&calendar);
+    let mut import = ICalImport::default();
+
+    for event in calendar.descendants("VEVENT") {
+        match parse_vevent(event, &zones, group_id) {
+            Ok(Some(post_event)) => import.events.push(post_event),
+            Ok(None) => {}
+            Err(reason) => import.skipped.push(ICalSkippedEvent {
+                uid: event.find("UID").map(|uid| unescape_text(&uid.value)),
+                name: event
+                    .find("SUMMARY")
+                    .map(|summary| unescape_text(&summary.value))
+                    .unwrap_or_default(),
+                reason,
+            }),
+        }
+    }
+
+    Ok(import)
+}
+
+/// A BEGIN / END block with its properties and nested blocks, the root holds the top level blocks
+#[derive(Default)]
+struct ICalComponent {
+    name: String,
+    properties: Vec<ICalProperty>,
+    components: Vec<ICalComponent>,
+}
+
+impl ICalComponent {
+    fn parse(input: &str) -> CanisterResult<Self> {
+        let mut stack = vec![Self::default()];
+
+        for line in unfold_lines(input) {
+            let property = ICalProperty::parse(&line)?;
+            match property.name.as_str() {
+                "BEGIN" => stack.push(Self {
+                    name: property.value.trim().to_uppercase(),
+                    ..Default::default()
+                }),
+                // components that are not closed are left out
+                "END" if stack.len() > 1 => {
+                    if let Some(component) = stack.pop() {
+                        if let Some(parent) = stack.last_mut() {
+                            parent.components.push(component);
+                        }
+                    }
+                }
+                "END" => {}
+                _ => {
+                    if let Some(component) = stack.last_mut() {
+                        component.properties.push(property);
+                    }
+                }
+            }
+        }
+
+        Ok(stack.into_iter().next().unwrap_or_default())
+    }
+
+    fn find(&self, name: &str) -> Option<&ICalProperty> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+
+    fn find_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a ICalProperty> + 'a {
+        self.properties
+            .iter()
+            .filter(move |property| property.name == name)
+    }
+
+    /// The nested components with the name at any depth, alarms inside events are not included
+    fn descendants(&self, name: &str) -> Vec<&ICalComponent> {
+        let mut found = vec![];
+        for component in &self.components {
+            match component.name == name {
+                true => found.push(component),
+                false => found.extend(component.descendants(name)),
+            }
+        }
+        found
+    }
+}
+
+struct ICalProperty {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl ICalProperty {
+    fn parse(line: &str) -> CanisterResult<Self> {
+        // the value starts at the first colon that is not inside a quoted parameter
+        let mut is_quoted = false;
+        let separator = line.char_indices().find_map(|(index, c)| match c {
+            '"' => {
+                is_quoted = !is_quoted;
+                None
+            }
+            ':' if !is_quoted => Some(index),
+            _ => None,
+        });
+
+        let Some(separator) = separator else {
+            return Err(ApiError::bad_request()
+                .add_method_name("parse_ical")
+                .add_message("Invalid iCalendar line")
+                .add_info(line));
+        };
+
+        let mut parts = line[..separator].split(';');
+        let name = parts.next().unwrap_or_default().to_uppercase();
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+            .collect();
+
+        Ok(Self {
+            name,
+            params,
+            value: line[separator + 1..].to_string(),
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn is_date(&self) -> bool {
+        self.param("VALUE")
+            .is_some_and(|value| value.eq_ignore_ascii_case("DATE"))
+            || !self.value.contains('T')
+    }
+}
+
+/// The timezones defined by the VTIMEZONEs of the file, by `TZID`
+struct ICalZones(BTreeMap<String, Result<ICalTimezone, String>>);
+
+impl ICalZones {
+    fn from_calendar(calendar: &ICalComponent) -> Self {
+        Self(
+            calendar
+                .descendants("VTIMEZONE")
+                .into_iter()
+                .filter_map(|timezone| {
+                    let tzid = timezone.find("TZID")?.value.trim().to_string();
+                    Some((tzid, ICalTimezone::parse(timezone)))
+                })
+                .collect(),
+        )
+    }
+
+    /// The offset in seconds at the local time, fixed offsets like `Etc/GMT-2` do not need a VTIMEZONE
+    fn offset_at(&self, tzid: &str, local: u64) -> Result<i64, String> {
+        if let Some(offset) = parse_utc_offset(tzid) {
+            return Ok(offset);
+        }
+
+        match self.0.get(tzid.trim()) {
+            Some(Ok(timezone)) => Ok(timezone.offset_at(local)),
+            Some(Err(reason)) => Err(reason.clone()),
+            None => Err(format!("Unknown timezone: {tzid}")),
+        }
+    }
+
+    /// Reads a DTSTART or DTEND value as UTC timestamp with the offset of its timezone
+    fn resolve(&self, property: &ICalProperty) -> Result<ICalTime, String> {
+        let local = parse_local_date_time(&property.value)?;
+
+        let offset = match (is_utc_date_time(&property.value), property.param("TZID")) {
+            (false, Some(tzid)) => Some(self.offset_at(tzid, local)?),
+            _ => None,
+        };
+
+        Ok(ICalTime {
+            utc: local_to_utc(local, offset.unwrap_or_default())?,
+            local,
+            offset,
+            tzid: property.param("TZID").map(str::to_string),
+        })
+    }
+}
+
+struct ICalTime {
+    utc: u64,
+    local: u64,
+    offset: Option<i64>,
+    tzid: Option<String>,
+}
+
+impl ICalTime {
+    /// Fixed offset timezones are kept, other timezones are stored as their offset at this time
+    fn timezone(&self) -> Option<String> {
+        match (&self.tzid, self.offset) {
+            (Some(tzid), _) if parse_utc_offset(tzid).is_some() => Some(tzid.clone()),
+            (_, Some(offset)) => Some(format_utc_offset(offset)),
+            _ => None,
+        }
+    }
+}
+
+/// A VTIMEZONE, its STANDARD and DAYLIGHT observances take turns from their onsets
+struct ICalTimezone {
+    observances: Vec<ICalObservance>,
+}
+
+impl ICalTimezone {
+    fn parse(timezone: &ICalComponent) -> Result<Self, String> {
+        let tzid = timezone
+            .find("TZID")
+            .map(|tzid| tzid.value.clone())
+            .unwrap_or_default();
+        let unsupported = |reason: &str| format!("Unsupported timezone {tzid}: {reason}");
+
+        let observances = timezone
+            .components
+            .iter()
+            .filter(|component| ["STANDARD", "DAYLIGHT"].contains(&component.name.as_str()))
+            .map(|observance| {
+                let offset = |name: &str| {
+                    observance
+                        .find(name)
+                        .and_then(|offset| parse_utc_offset(&offset.value))
+                        .ok_or_else(|| unsupported(name))
+                };
+
+                Ok(ICalObservance {
+                    start: observance
+                        .find("DTSTART")
+                        .and_then(|start| parse_civil_date_time(&start.value))
+                        // observances are often defined from 1601, they apply from 1970 on here
+                        .map(|(days, time_of_day)| match days < 0 {
+                            true => 0,
+                            false => days_to_nanoseconds(days) + time_of_day,
+                        })
+                        .ok_or_else(|| unsupported("DTSTART"))?,
+                    offset_from: offset("TZOFFSETFROM")?,
+                    offset_to: offset("TZOFFSETTO")?,
+                    rule: match observance.find("RRULE") {
+                        Some(rrule) => Some(
+                            ICalYearlyRule::parse(&rrule.value)
+                                .ok_or_else(|| unsupported(&rrule.value))?,
+                        ),
+                        None => None,
+                    },
+                })
+            })
+            .collect::<Result<Vec<ICalObservance>, String>>()?;
+
+        match observances.is_empty() {
+            true => Err(unsupported("no observances")),
+            false => Ok(Self { observances }),
+        }
+    }
+
+    fn offset_at(&self, local: u64) -> i64 {
+        self.observances
+            .iter()
+            .filter_map(|observance| {
+                observance
+                    .last_onset(local)
+                    .map(|onset| (onset, observance.offset_to))
+            })
+            .max_by_key(|(onset, _)| *onset)
+            .map(|(_, offset)| offset)
+            // before the first onset the offset the first observance changes from applies
+            .or_else(|| {
+                self.observances
+                    .iter()
+                    .min_by_key(|observance| observance.start)
+                    .map(|observance| observance.offset_from)
+            })
+            .unwrap_or_default()
+    }
+}
+
+struct ICalObservance {
+    /// The local time of the first onset
+    start: u64,
+    offset_from: i64,
+    offset_to: i64,
+    rule: Option<ICalYearlyRule>,
+}
+
+impl ICalObservance {
+    /// The latest onset at or before the local time
+    fn last_onset(&self, local: u64) -> Option<u64> {
+        if local < self.start {
+            return None;
+        }
+
+        let Some(rule) = &self.rule else {
+            return Some(self.start);
+        };
+
+        let time_of_day = split_nanoseconds(self.start).1;
+        let until = rule.until.unwrap_or(u64::MAX).min(local);
+        let (year, _, _) = civil_from_days(split_nanoseconds(until).0);
+
+        [year, year - 1]
+            .into_iter()
+            .filter_map(|year| rule.onset(year, time_of_day))
+            .filter(|onset| *onset >= self.start && *onset <= until)
+            .max()
+            .or(Some(self.start))
+    }
+}
+
+/// `FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU`, the form timezone databases use for daylight saving time changes
+struct ICalYearlyRule {
+    month: u32,
+    /// 1 for the first weekday of the month, -1 for the last
+    week: i64,
+    weekday: Weekday,
+    until: Option<u64>,
+}
+
+impl ICalYearlyRule {
+    fn parse(value: &str) -> Option<Self> {
+        let (mut month, mut by_day, mut until, mut is_yearly) = (None, None, None, false);
+
+        for (key, value) in value.split(';').filter_map(|part| part.split_once('=')) {
+            match key.to_uppercase().as_str() {
+                "FREQ" => is_yearly = value.eq_ignore_ascii_case("YEARLY"),
+                "INTERVAL" if value == "1" => {}
+                "BYMONTH" => {
+                    month = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|month| (1..=12).contains(month))
+                }
+                "BYDAY" => {
+                    let (week, weekday) = value.split_at_checked(value.len().checked_sub(2)?)?;
+                    by_day = Some((week.parse::<i64>().ok()?, parse_weekday(weekday)?));
+                }
+                "UNTIL" => until = Some(parse_local_date_time(value).ok()?),
+                "WKST" => {}
+                _ => return None,
+            }
+        }
+
+        let (week, weekday) = by_day.filter(|(week, _)| *week != 0 && week.abs() <= 5)?;
+        is_yearly.then_some(Self {
+            month: month?,
+            week,
+            weekday,
+            until,
+        })
+    }
+
+    fn onset(&self, year: i64, time_of_day: u64) -> Option<u64> {
+        let first = days_from_civil(year, self.month, 1);
+        let last = first + days_in_month(year, self.month) as i64 - 1;
+
+        let day = match self.week > 0 {
+            true => {
+                let delta = (self.weekday.offset() - weekday_from_days(first) as i64).rem_euclid(7);
+                first + delta + (self.week - 1) * 7
+            }
+            false => {
+                let delta = (weekday_from_days(last) as i64 - self.weekday.offset()).rem_euclid(7);
+                last - delta + (self.week + 1) * 7
+            }
+        };
+
+        (first..=last)
+            .contains(&day)
+            .then(|| days_to_nanoseconds(day).saturating_add(time_of_day))
+    }
+}
+
+fn parse_vevent(
+    event: &ICalComponent,
+    zones: &ICalZones,
+    group_id: Option<u64>,
+) -> Result<Option<PostEvent>, String> {
+    if event
+        .find("STATUS")
+        .is_some_and(|status| status.value.eq_ignore_ascii_case("CANCELLED"))
+    {
+        return Ok(None);
+    }
+
+    let name = event
+        .find("SUMMARY")
+        .map(|summary| unescape_text(&summary.value))
+        .unwrap_or_default();
+
+    let start_property = event
+        .find("DTSTART")
+        .ok_or_else(|| "Event has no DTSTART".to_string())?;
+    let start = zones.resolve(start_property)?;
+
+    let end_date = match event.find("DTEND") {
+        Some(end) => zones.resolve(end)?.utc,
+        // all day events without an end last one day, other events are instantaneous
+        None if start_property.is_date() => start.utc + NANOSECONDS_PER_DAY,
+        None => start.utc,
+    };
+
+    let recurrence = match event.find("RRULE") {
+        Some(rrule) => {
+            let mut recurrence = parse_rrule(&rrule.value, start.offset.unwrap_or_default())?;
+            for exdate in event.find_all("EXDATE") {
+                for value in exdate.value.split(',') {
+                    recurrence.exceptions.push(parse_exdate(
+                        value,
+                        exdate,
+                        start_property,
+                        &start,
+                    )?);
+                }
+            }
+            Some(recurrence)
+        }
+        None => None,
+    };
+
+    let url = event.find("URL").map(|url| url.value.trim().to_string());
+    let location_text = event
+        .find("LOCATION")
+        .map(|location| unescape_text(&location.value));
+    let geo = event.find("GEO").and_then(|geo| {
+        let (lat, lng) = geo.value.split_once([';', ','])?;
+        Some((
+            lat.trim().parse::<f32>().ok()?,
+            lng.trim().parse::<f32>().ok()?,
+        ))
+    });
+
+    let is_digital = location_text.as_ref().is_some_and(|location| {
+        location.starts_with("http://") || location.starts_with("https://")
+    });
+
+    let location = match (location_text, geo) {
+        (Some(location), _) if is_digital => Location::Digital(location),
+        (Some(location), geo) => physical_location(location, geo),
+        (None, Some(geo)) => physical_location(String::default(), Some(geo)),
+        (None, None) => Location::None,
+    };
+
+    let mut date = DateRange::new(start.utc, end_date.max(start.utc));
+    date.timezone = start.timezone();
+
+    Ok(Some(PostEvent {
+        name,
+        description: event
+            .find("DESCRIPTION")
+            .map(|description| unescape_text(&description.value))
+            .unwrap_or_default(),
+        date,
+        recurrence,
+        capacity: None,
+        privacy: PrivacyType::default(),
+        website: url.unwrap_or_default(),
+        location,
+        image: Asset::None,
+        banner_image: Asset::None,
+        group_id,
+        metadata: None,
+        tags: vec![],
+    }))
+}
+
+/// The start of the excluded occurrence. Occurrences are expanded in the fixed offset of the start, so EXDATEs are
+/// read with that offset and a date without a time excludes the occurrence on that day.
+fn parse_exdate(
+    value: &str,
+    exdate: &ICalProperty,
+    start_property: &ICalProperty,
+    start: &ICalTime,
+) -> Result<u64, String> {
+    let local = parse_local_date_time(value)?;
+
+    if is_utc_date_time(value) {
+        return Ok(local);
+    }
+
+    let local = match exdate.is_date() && !start_property.is_date() {
+        true => local + split_nanoseconds(start.local).1,
+        false => local,
+    };
+    local_to_utc(local, start.offset.unwrap_or_default())
+}
+
+fn physical_location(label: String, geo: Option<(f32, f32)>) -> Location {
+    let (lattitude, longtitude) = geo.unwrap_or_default();
+    Location::Physical(PhysicalLocation {
+        lattitude,
+        longtitude,
+        address: Address {
+            label,
+            ..Default::default()
+        },
+    })
+}
+
+fn format_address(address: &Address) -> String {
+    let street = [
+        address.street.as_str(),
+        address.house_number.as_str(),
+        address.house_number_addition.as_str(),
+    ];
+    let city = [address.postal_code.as_str(), address.city.as_str()];
+
+    [
+        address.label.clone(),
+        join_non_empty(&street, " "),
+        join_non_empty(&city, " "),
+        address.state_or_province.clone(),
+        address.country.clone(),
+    ]
+    .iter()
+    .filter(|part| !part.trim().is_empty())
+    .map(|part| part.trim())
+    .collect::<Vec<&str>>()
+    .join(", ")
+}
+
+fn join_non_empty(parts: &[&str], separator: &str) -> String {
+    parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join(separator)
+}
+
+fn format_rrule(recurrence: &Recurrence) -> String {
+    let frequency = match recurrence.frequency {
+        RecurrenceFrequency::Daily => "DAILY",
+        RecurrenceFrequency::Weekly => "WEEKLY",
+        RecurrenceFrequency::Monthly => "MONTHLY",
+    };
+
+    let mut parts = vec![
+        format!("FREQ={frequency}"),
+        format!("INTERVAL={}", recurrence.interval.max(1)),
+    ];
+
+    if !recurrence.by_weekday.is_empty() {
+        let weekdays: Vec<&str> = recurrence.by_weekday.iter().map(format_weekday).collect();
+        parts.push(format!("BYDAY={}", weekdays.join(",")));
+    }
+
+    if let Some(count) = recurrence.count {
+        parts.push(format!("COUNT={count}"));
+    }
+
+    if let Some(until) = recurrence.until {
+        parts.push(format!("UNTIL={}", format_date_time(until)));
+    }
+
+    parts.join(";")
+}
+
+/// `UNTIL` values without a `Z` suffix are read with the offset of the start
+fn parse_rrule(value: &str, offset: i64) -> Result<Recurrence, String> {
+    let mut recurrence = Recurrence {
+        frequency: RecurrenceFrequency::Daily,
+        interval: 1,
+        by_weekday: vec![],
+        count: None,
+        until: None,
+        exceptions: vec![],
+    };
+
+    for (key, value) in value.split(';').filter_map(|part| part.split_once('=')) {
+        match key.to_uppercase().as_str() {
+            "FREQ" => {
+                recurrence.frequency = match value.to_uppercase().as_str() {
+                    "DAILY" => RecurrenceFrequency::Daily,
+                    "WEEKLY" => RecurrenceFrequency::Weekly,
+                    "MONTHLY" => RecurrenceFrequency::Monthly,
+                    _ => return Err(unsupported_rrule(format!("FREQ={value}"))),
+                }
+            }
+            "INTERVAL" => {
+                recurrence.interval = value
+                    .parse()
+                    .map_err(|_| unsupported_rrule(format!("INTERVAL={value}")))?
+            }
+            "COUNT" => {
+                recurrence.count = Some(
+                    value
+                        .parse()
+                        .map_err(|_| unsupported_rrule(format!("COUNT={value}")))?,
+                )
+            }
+            "UNTIL" => {
+                let until = parse_local_date_time(value)
+                    .map_err(|_| unsupported_rrule(format!("UNTIL={value}")))?;
+                recurrence.until = Some(match is_utc_date_time(value) {
+                    true => until,
+                    false => local_to_utc(until, offset)?,
+                });
+            }
+            "BYDAY" => {
+                for weekday in value.split(',') {
+                    recurrence.by_weekday.push(
+                        parse_weekday(weekday)
+                            .ok_or_else(|| unsupported_rrule(format!("BYDAY={weekday}")))?,
+                    );
+                }
+            }
+            "WKST" => {}
+            _ => return Err(unsupported_rrule(format!("{key}={value}"))),
+        }
+    }
+
+    recurrence.validate().map_err(|err| err.to_string())?;
+    Ok(recurrence)
+}
+
+fn unsupported_rrule(part: String) -> String {
+    format!("Unsupported recurrence rule: {part}")
+}
+
+fn format_weekday(weekday: &Weekday) -> &'static str {
+    use Weekday::*;
+    match weekday {
+        Monday => "MO",
+        Tuesday => "TU",
+        Wednesday => "WE",
+        Thursday => "TH",
+        Friday => "FR",
+        Saturday => "SA",
+        Sunday => "SU",
+    }
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    use Weekday::*;
+    match value.trim().to_uppercase().as_str() {
+        "MO" => Some(Monday),
+        "TU" => Some(Tuesday),
+        "WE" => Some(Wednesday),
+        "TH" => Some(Thursday),
+        "FR" => Some(Friday),
+        "SA" => Some(Saturday),
+        "SU" => Some(Sunday),
+        _ => None,
+    }
+}
+
+/// Formats a nanosecond timestamp as an UTC date-time like `20240131T100000Z`
+pub fn format_date_time(timestamp: u64) -> String {
+    let (days, nanoseconds) = split_nanoseconds(timestamp);
+    let (year, month, day) = civil_from_days(days);
+    let seconds = nanoseconds / NANOSECONDS_PER_SECOND;
+
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Formats a nanosecond timestamp as local date-time like `20240131T120000` for the offset in seconds
+fn format_local_date_time(timestamp: u64, offset_seconds: i64) -> String {
+    let local = timestamp as i128 + offset_seconds as i128 * NANOSECONDS_PER_SECOND as i128;
+    format_date_time(local.clamp(0, u64::MAX as i128) as u64)
+        .trim_end_matches('Z')
+        .to_string()
+}
+
+/// Parses `YYYYMMDD`, `YYYYMMDDTHHMMSS` and `YYYYMMDDTHHMMSSZ` into a nanosecond timestamp, the time is read as UTC.
+/// Dates before 1970 can not be represented and are rejected.
+pub fn parse_date_time(value: &str) -> CanisterResult<u64> {
+    parse_local_date_time(value).map_err(|reason| {
+        ApiError::bad_request()
+            .add_method_name("parse_ical")
+            .add_message(reason)
+    })
+}
+
+/// The date and time as if it was UTC, the `Z` suffix is ignored
+fn parse_local_date_time(value: &str) -> Result<u64, String> {
+    let (days, time_of_day) =
+        parse_civil_date_time(value).ok_or_else(|| format!("Invalid iCalendar date: {value}"))?;
+
+    match days < 0 {
+        true => Err(format!("Date is before 1970: {value}")),
+        false => Ok(days_to_nanoseconds(days) + time_of_day),
+    }
+}
+
+/// The days since 1970-01-01, negative before 1970, and the nanoseconds of the day
+fn parse_civil_date_time(value: &str) -> Option<(i64, u64)> {
+    let value = value.trim().trim_end_matches(['Z', 'z']);
+    let (date, time) = value.split_once(['T', 't']).unwrap_or((value, "000000"));
+
+    if date.len() != 8 || time.len() != 6 || !date.is_ascii() || !time.is_ascii() {
+        return None;
+    }
+
+    let number = |part: &str| part.parse::<u32>().ok();
+    let (year, month, day) = (
+        number(&date[0..4])?,
+        number(&date[4..6])?,
+        number(&date[6..8])?,
+    );
+    let (hours, minutes, seconds) = (
+        number(&time[0..2])?,
+        number(&time[2..4])?,
+        number(&time[4..6])?,
+    );
+
+    if !(1..=12).contains(&month)
+        || day == 0
+        || day > days_in_month(year as i64, month)
+        || hours > 23
+        || minutes > 59
+        || seconds > 60
+    {
+        return None;
+    }
+
+    let seconds_of_day = (hours * 3600 + minutes * 60 + seconds) as u64;
+    Some((
+        days_from_civil(year as i64, month, day),
+        seconds_of_day * NANOSECONDS_PER_SECOND,
+    ))
+}
+
+fn is_utc_date_time(value: &str) -> bool {
+    value.trim().ends_with(['Z', 'z'])
+}
+
+fn local_to_utc(local: u64, offset_seconds: i64) -> Result<u64, String> {
+    let utc = local as i128 - offset_seconds as i128 * NANOSECONDS_PER_SECOND as i128;
+    u64::try_from(utc).map_err(|_| "Date is before 1970".to_string())
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => result.push('\n'),
+                Some(escaped) => result.push(escaped),
+                None => result.push('\\'),
+            },
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Splits the line into chunks of at most 75 octets without breaking up UTF-8 characters
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut line_length = 0;
+
+    for c in line.chars() {
+        if line_length + c.len_utf8() > ICAL_MAX_LINE_LENGTH {
+            folded.push_str(ICAL_LINE_BREAK);
+            folded.push(' ');
+            // the leading space counts towards the length of the continuation line
+            line_length = 1;
+        }
+        folded.push(c);
+        line_length += c.len_utf8();
+    }
+    folded
+}
+
+fn unfold_lines(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in input.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NANOSECONDS_PER_HOUR: u64 = 3600 * NANOSECONDS_PER_SECOND;
+
+    fn calendar(body: &str) -> String {
+        format!("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{body}END:VCALENDAR\r\n")
+    }
+
+    fn utc(value: &str) -> u64 {
+        parse_local_date_time(value).expect("valid date")
+    }
+
+    const AMSTERDAM: &str = "BEGIN:VTIMEZONE\r\n\
+        TZID:Europe/Amsterdam\r\n\
+        BEGIN:DAYLIGHT\r\n\
+        DTSTART:19810329T020000\r\n\
+        RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r\n\
+        TZOFFSETFROM:+0100\r\n\
+        TZOFFSETTO:+0200\r\n\
+        END:DAYLIGHT\r\n\
+        BEGIN:STANDARD\r\n\
+        DTSTART:19961027T030000\r\n\
+        RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r\n\
+        TZOFFSETFROM:+0200\r\n\
+        TZOFFSETTO:+0100\r\n\
+        END:STANDARD\r\n\
+        END:VTIMEZONE\r\n";
+
+    #[test]
+    fn parse_ical_reads_fixed_offset_tzid() {
+        let input = calendar(
+            "BEGIN:VEVENT\r\nUID:1\r\nSUMMARY:Meetup\r\n\
+             DTSTART;TZID=Etc/GMT-2:20240601T120000\r\n\
+             DTEND;TZID=Etc/GMT-2:20240601T140000\r\nEND:VEVENT\r\n",
+        );
+
+        let import = parse_ical(&input, None).expect("valid calendar");
+        let date = &import.events[0].date;
+
+        assert_eq!(date.start_date(), utc("20240601T100000Z"));
+        assert_eq!(date.end_date(), utc("20240601T120000Z"));
+        assert_eq!(date.timezone.as_deref(), Some("Etc/GMT-2"));
+    }
+
+    #[test]
+    fn parse_ical_follows_vtimezone_daylight_saving_time() {
+        let input = calendar(&format!(
+            "{AMSTERDAM}\
+             BEGIN:VEVENT\r\nUID:summer\r\nDTSTART;TZID=Europe/Amsterdam:20240701T120000\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:winter\r\nDTSTART;TZID=\"Europe/Amsterdam\":20240115T120000\r\nEND:VEVENT\r\n"
+        ));
+
+        let import = parse_ical(&input, None).expect("valid calendar");
+        let (summer, winter) = (&import.events[0].date, &import.events[1].date);
+
+        assert_eq!(summer.start_date(), utc("20240701T100000Z"));
+        assert_eq!(summer.timezone.as_deref(), Some("+02:00"));
+        assert_eq!(winter.start_date(), utc("20240115T110000Z"));
+        assert_eq!(winter.timezone.as_deref(), Some("+01:00"));
+    }
+
+    #[test]
+    fn parse_ical_matches_date_only_exdate_with_timed_start() {
+        let input = calendar(
+            "BEGIN:VEVENT\r\nUID:1\r\n\
+             DTSTART;TZID=Etc/GMT-2:20240101T100000\r\n\
+             DTEND;TZID=Etc/GMT-2:20240101T110000\r\n\
+             RRULE:FREQ=DAILY;COUNT=5\r\nEXDATE;VALUE=DATE:20240103\r\nEND:VEVENT\r\n",
+        );
+
+        let import = parse_ical(&input, None).expect("valid calendar");
+        let event = &import.events[0];
+        let recurrence = event.recurrence.as_ref().expect("recurrence");
+
+        assert_eq!(recurrence.exceptions, vec![utc("20240103T080000Z")]);
+        let starts: Vec<u64> = recurrence
+            .occurrences(&event.date)
+            .map(|date| date.start_date())
+            .collect();
+        assert_eq!(starts.len(), 4);
+        assert!(!starts.contains(&utc("20240103T080000Z")));
+        assert!(starts.contains(&utc("20240104T080000Z")));
+    }
+
+    #[test]
+    fn parse_ical_skips_unsupported_events() {
+        let input = calendar(
+            "BEGIN:VEVENT\r\nUID:yearly\r\nSUMMARY:Birthday\r\nDTSTART:20240101T100000Z\r\n\
+             RRULE:FREQ=YEARLY\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:unknown\r\nDTSTART;TZID=Mars/Olympus:20240101T100000\r\nEND:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:weekly\r\nSUMMARY:Standup\r\nDTSTART:20240101T100000Z\r\n\
+             RRULE:FREQ=WEEKLY;BYDAY=MO,WE\r\nEND:VEVENT\r\n",
+        );
+
+        let import = parse_ical(&input, None).expect("valid calendar");
+
+        assert_eq!(import.events.len(), 1);
+        assert_eq!(import.events[0].name, "Standup");
+        let skipped: Vec<Option<&str>> = import
+            .skipped
+            .iter()
+            .map(|skipped| skipped.uid.as_deref())
+            .collect();
+        assert_eq!(skipped, vec![Some("yearly"), Some("unknown")]);
+        assert_eq!(import.skipped[0].name, "Birthday");
+    }
+
+    #[test]
+    fn parse_ical_skips_events_before_1970() {
+        let input = calendar(
+            "BEGIN:VEVENT\r\nUID:moon\r\nSUMMARY:Moon landing\r\nDTSTART:19690720T201700Z\r\n\
+             END:VEVENT\r\n\
+             BEGIN:VEVENT\r\nUID:epoch\r\nSUMMARY:Epoch\r\nDTSTART:19700101T000000Z\r\nEND:VEVENT\r\n",
+        );
+
+        let import = parse_ical(&input, None).expect("valid calendar");
+
+        assert_eq!(import.events.len(), 1);
+        assert_eq!(import.events[0].date.start_date(), 0);
+        assert_eq!(import.skipped.len(), 1);
+        assert_eq!(import.skipped[0].uid.as_deref(), Some("moon"));
+        assert_eq!(
+            import.skipped[0].reason,
+            "Date is before 1970: 19690720T201700Z"
+        );
+        assert!(parse_date_time("19691231").is_err());
+    }
+
+    #[test]
+    fn to_ical_calendar_writes_the_date_timezone() {
+        let start = utc("20240601T100000Z");
+        let event = ICalEvent {
+            uid: "event-1@catalyze.one".to_string(),
+            summary: "Meetup".to_string(),
+            date: DateRange::new(start, start + 2 * NANOSECONDS_PER_HOUR).with_timezone("+02:00"),
+            ..Default::default()
+        };
+
+        let output = to_ical_calendar(&[event], None);
+
+        assert!(output.contains("TZID:+02:00\r\n"));
+        assert!(output.contains("TZOFFSETTO:+0200\r\n"));
+        assert!(output.contains("DTSTART;TZID=\"+02:00\":20240601T120000\r\n"));
+
+        let import = parse_ical(&output, None).expect("valid calendar");
+        let date = &import.events[0].date;
+        assert_eq!(date.start_date(), start);
+        assert_eq!(date.end_date(), start + 2 * NANOSECONDS_PER_HOUR);
+        assert_eq!(date.timezone.as_deref(), Some("+02:00"));
+    }
+}
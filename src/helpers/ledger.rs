@@ -0,0 +1,629 @@
+use std::future::Future;
+
+use candid::{types::reference::Func, CandidType, Nat, Principal};
+use icrc_ledger_types::{
+    icrc::generic_value::Value,
+    icrc1::{
+        account::{Account, Subaccount},
+        transfer::{Memo, TransferArg, TransferError},
+    },
+    icrc2::transfer_from::{TransferFromArgs, TransferFromError},
+    icrc3::{archive::QueryArchiveFn, blocks::GetBlocksRequest},
+};
+use serde::Deserialize;
+use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha224};
+
+use crate::{api_error::ApiError, CanisterResult};
+
+/// The ICP ledger does not implement ICRC-3, its blocks are read with `query_blocks`
+pub const ICP_LEDGER_CANISTER_ID: &str = "ryjl3-tyaaa-aaaaa-aaaba-cai";
+
+// ICRC-3 operations and block types that move tokens from one account to another
+const ICRC3_TRANSFER_OP: &str = "xfer";
+const ICRC3_TRANSFER_BLOCK_TYPES: [&str; 2] = ["1xfer", "2xfer"];
+
+/// The ICRC ledgers and the ICP ledger, calls the ledger canister that is passed to the trait methods
+pub struct IcrcLedger;
+
+/// Source of token balances, implemented by `IcrcLedger` for the real ledgers and by stand-ins in tests
+pub trait TokenLedger {
+    fn balance_of(
+        &self,
+        ledger: Principal,
+        account: Account,
+    ) -> impl Future<Output = CanisterResult<Nat>>;
+}
+
+/// Source of ledger blocks, implemented by `IcrcLedger` for the real ledgers and by stand-ins in tests
+pub trait TransactionLedger {
+    /// The transfer in the block, `None` when the block does not exist. Blocks that do not move tokens
+    /// between two accounts, like mints and approvals, are refused.
+    fn get_transfer(
+        &self,
+        ledger: Principal,
+        blockheight: u64,
+    ) -> impl Future<Output = CanisterResult<Option<BlockTransfer>>>;
+}
+
+/// The sending or receiving side of a transfer block, ICP blocks only carry the account identifier
+#[derive(CandidType, Clone, Debug, PartialEq, Eq, Deserialize)]
+pub enum BlockAccount {
+    Account(Account),
+    AccountIdentifier(ByteBuf),
+}
+
+impl BlockAccount {
+    /// Any subaccount of the principal for ICRC blocks, only its default account for ICP blocks
+    pub fn is_owned_by(&self, principal: Principal) -> bool {
+        match self {
+            BlockAccount::Account(account) => account.owner == principal,
+            BlockAccount::AccountIdentifier(identifier) => {
+                identifier.as_slice() == account_identifier(&Account::from(principal))
+            }
+        }
+    }
+
+    pub fn is_account(&self, account: &Account) -> bool {
+        match self {
+            BlockAccount::Account(block_account) => block_account == account,
+            BlockAccount::AccountIdentifier(identifier) => {
+                identifier.as_slice() == account_identifier(account)
+            }
+        }
+    }
+}
+
+#[derive(CandidType, Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct BlockTransfer {
+    pub from: BlockAccount,
+    pub to: BlockAccount,
+    pub amount: Nat,
+    pub memo: Option<Memo>,
+}
+
+/// The ICP account identifier of the account, the CRC32 of the SHA-224 hash followed by the hash
+pub fn account_identifier(account: &Account) -> [u8; 32] {
+    let mut hasher = Sha224::new();
+    hasher.update(b"\x0Aaccount-id");
+    hasher.update(account.owner.as_slice());
+    hasher.update(account.subaccount.unwrap_or([0; 32]));
+    let hash = hasher.finalize();
+
+    let mut identifier = [0; 32];
+    identifier[..4].copy_from_slice(&crc32fast::hash(&hash).to_be_bytes());
+    identifier[4..].copy_from_slice(&hash);
+    identifier
+}
+
+// `icrc3_get_blocks` types, not part of the ICRC ledger types version this crate depends on
+#[derive(CandidType, Deserialize)]
+struct GetBlocksResult {
+    blocks: Vec<BlockWithId>,
+    archived_blocks: Vec<ArchivedBlocks>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct BlockWithId {
+    id: Nat,
+    block: Value,
+}
+
+#[derive(CandidType, Deserialize)]
+struct ArchivedBlocks {
+    args: Vec<GetBlocksRequest>,
+    callback: QueryArchiveFn<Vec<GetBlocksRequest>, GetBlocksResult>,
+}
+
+// `query_blocks` types of the ICP ledger, only the fields that are read
+#[derive(CandidType, Deserialize)]
+struct IcpGetBlocksArgs {
+    start: u64,
+    length: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+struct IcpQueryBlocksResponse {
+    first_block_index: u64,
+    blocks: Vec<IcpBlock>,
+    archived_blocks: Vec<IcpArchivedBlocksRange>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct IcpArchivedBlocksRange {
+    start: u64,
+    length: u64,
+    callback: Func,
+}
+
+#[derive(CandidType, Deserialize)]
+struct IcpBlockRange {
+    blocks: Vec<IcpBlock>,
+}
+
+#[derive(CandidType, Deserialize)]
+struct IcpBlock {
+    transaction: IcpTransaction,
+}
+
+#[derive(CandidType, Deserialize)]
+struct IcpTransaction {
+    icrc1_memo: Option<ByteBuf>,
+    operation: Option<IcpOperation>,
+}
+
+#[derive(CandidType, Deserialize)]
+enum IcpOperation {
+    Transfer {
+        from: ByteBuf,
+        to: ByteBuf,
+        amount: IcpTokens,
+    },
+    Mint(candid::Reserved),
+    Burn(candid::Reserved),
+    Approve(candid::Reserved),
+}
+
+#[derive(CandidType, Deserialize)]
+struct IcpTokens {
+    e8s: u64,
+}
+
+/// Moves tokens on behalf of the canister, both methods return the block height of the transfer
+pub trait TransferLedger {
+    fn transfer(
+        &self,
+        ledger: Principal,
+        args: TransferArg,
+    ) -> impl Future<Output = CanisterResult<u64>>;
+
+    fn transfer_from(
+        &self,
+        ledger: Principal,
+        args: TransferFromArgs,
+    ) -> impl Future<Output = CanisterResult<u64>>;
+}
+
+impl TokenLedger for IcrcLedger {
+    async fn balance_of(&self, ledger: Principal, account: Account) -> CanisterResult<Nat> {
+        let (balance,): (Nat,) = ic_cdk::call(ledger, "icrc1_balance_of", (account,))
+            .await
+            .map_err(|e| {
+                ApiError::unexpected()
+                    .add_message("Failed to call ledger")
+                    .add_info(format!("Canister: {ledger} error: {:?}", e))
+                    .add_method_name("icrc1_balance_of")
+            })?;
+
+        Ok(balance)
+    }
+}
+
+impl TransactionLedger for IcrcLedger {
+    /// Reads the block with `icrc3_get_blocks`, or `query_blocks` for the ICP ledger, and follows the
+    /// archive callback when it is archived
+    async fn get_transfer(
+        &self,
+        ledger: Principal,
+        blockheight: u64,
+    ) -> CanisterResult<Option<BlockTransfer>> {
+        match ledger.to_text() == ICP_LEDGER_CANISTER_ID {
+            true => get_icp_transfer(ledger, blockheight).await,
+            false => get_icrc3_transfer(ledger, blockheight).await,
+        }
+    }
+}
+
+async fn get_icrc3_transfer(
+    ledger: Principal,
+    blockheight: u64,
+) -> CanisterResult<Option<BlockTransfer>> {
+    let request = vec![GetBlocksRequest {
+        start: Nat::from(blockheight),
+        length: Nat::from(1u64),
+    }];
+
+    let (response,): (GetBlocksResult,) =
+        ic_cdk::call(ledger, "icrc3_get_blocks", (request.clone(),))
+            .await
+            .map_err(|e| {
+                ApiError::unexpected()
+                    .add_message("Failed to call ledger")
+                    .add_info(format!("Canister: {ledger} error: {:?}", e))
+                    .add_method_name("icrc3_get_blocks")
+            })?;
+
+    let mut blocks = response.blocks;
+    if let Some(archived) = response.archived_blocks.into_iter().find(|archived| {
+        archived.args.iter().any(|args| {
+            args.start <= blockheight && args.start.clone() + args.length.clone() > blockheight
+        })
+    }) {
+        let (range,): (GetBlocksResult,) = ic_cdk::call(
+            archived.callback.canister_id,
+            &archived.callback.method,
+            (request,),
+        )
+        .await
+        .map_err(|e| {
+            ApiError::unexpected()
+                .add_message("Failed to call ledger archive")
+                .add_info(format!(
+                    "Canister: {} error: {:?}",
+                    archived.callback.canister_id, e
+                ))
+                .add_method_name(&archived.callback.method)
+        })?;
+        blocks = range.blocks;
+    }
+
+    blocks
+        .into_iter()
+        .find(|block| block.id == blockheight)
+        .map(|block| icrc3_block_to_transfer(&block.block))
+        .transpose()
+        .map_err(|err| {
+            err.add_method_name("icrc3_get_blocks")
+                .add_info(blockheight)
+        })
+}
+
+async fn get_icp_transfer(
+    ledger: Principal,
+    blockheight: u64,
+) -> CanisterResult<Option<BlockTransfer>> {
+    let args = IcpGetBlocksArgs {
+        start: blockheight,
+        length: 1,
+    };
+
+    let (response,): (IcpQueryBlocksResponse,) = ic_cdk::call(ledger, "query_blocks", (&args,))
+        .await
+        .map_err(|e| {
+            ApiError::unexpected()
+                .add_message("Failed to call ledger")
+                .add_info(format!("Canister: {ledger} error: {:?}", e))
+                .add_method_name("query_blocks")
+        })?;
+
+    let block = match response.archived_blocks.into_iter().find(|range| {
+        range.start <= blockheight && range.start.saturating_add(range.length) > blockheight
+    }) {
+        Some(archived) => {
+            let (range,): (Result<IcpBlockRange, candid::Reserved>,) = ic_cdk::call(
+                archived.callback.principal,
+                &archived.callback.method,
+                (&args,),
+            )
+            .await
+            .map_err(|e| {
+                ApiError::unexpected()
+                    .add_message("Failed to call ledger archive")
+                    .add_info(format!(
+                        "Canister: {} error: {:?}",
+                        archived.callback.principal, e
+                    ))
+                    .add_method_name(&archived.callback.method)
+            })?;
+
+            range
+                .map_err(|_| {
+                    ApiError::unexpected()
+                        .add_message("Ledger archive refused the block range")
+                        .add_info(blockheight)
+                        .add_method_name(&archived.callback.method)
+                })?
+                .blocks
+                .into_iter()
+                .next()
+        }
+        None if response.first_block_index == blockheight => response.blocks.into_iter().next(),
+        None => None,
+    };
+
+    let Some(block) = block else {
+        return Ok(None);
+    };
+
+    match block.transaction.operation {
+        Some(IcpOperation::Transfer { from, to, amount }) => Ok(Some(BlockTransfer {
+            from: BlockAccount::AccountIdentifier(from),
+            to: BlockAccount::AccountIdentifier(to),
+            amount: Nat::from(amount.e8s),
+            memo: block.transaction.icrc1_memo.map(Memo::from),
+        })),
+        _ => Err(not_a_transfer()
+            .add_method_name("query_blocks")
+            .add_info(blockheight)),
+    }
+}
+
+fn icrc3_block_to_transfer(block: &Value) -> CanisterResult<BlockTransfer> {
+    let tx = map_field(block, "tx")
+        .ok_or_else(|| ApiError::unexpected().add_message("Block has no transaction"))?;
+
+    let is_transfer = text_field(tx, "op").is_some_and(|op| op == ICRC3_TRANSFER_OP)
+        || text_field(block, "btype")
+            .is_some_and(|btype| ICRC3_TRANSFER_BLOCK_TYPES.contains(&btype));
+
+    let (Some(from), Some(to), Some(amount), true) = (
+        map_field(tx, "from").and_then(value_to_account),
+        map_field(tx, "to").and_then(value_to_account),
+        map_field(tx, "amt").and_then(value_to_nat),
+        is_transfer,
+    ) else {
+        return Err(not_a_transfer());
+    };
+
+    let memo = match map_field(tx, "memo") {
+        Some(Value::Blob(memo)) => Some(Memo::from(memo.clone())),
+        _ => None,
+    };
+
+    Ok(BlockTransfer {
+        from: BlockAccount::Account(from),
+        to: BlockAccount::Account(to),
+        amount,
+        memo,
+    })
+}
+
+fn not_a_transfer() -> ApiError {
+    ApiError::bad_request().add_message("Block is not a transfer")
+}
+
+fn map_field<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Map(map) => map.get(key),
+        _ => None,
+    }
+}
+
+fn text_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    match map_field(value, key) {
+        Some(Value::Text(text)) => Some(text.as_str()),
+        _ => None,
+    }
+}
+
+fn value_to_nat(value: &Value) -> Option<Nat> {
+    match value {
+        Value::Nat(nat) => Some(nat.clone()),
+        Value::Nat64(nat) => Some(Nat::from(*nat)),
+        _ => None,
+    }
+}
+
+// accounts are encoded as an array with the owner and an optional subaccount
+fn value_to_account(value: &Value) -> Option<Account> {
+    let Value::Array(parts) = value else {
+        return None;
+    };
+
+    let owner = match parts.first() {
+        Some(Value::Blob(owner)) => Principal::try_from_slice(owner).ok()?,
+        _ => return None,
+    };
+
+    let subaccount = match parts.get(1) {
+        Some(Value::Blob(subaccount)) => Some(Subaccount::try_from(subaccount.as_slice()).ok()?),
+        None => None,
+        _ => return None,
+    };
+
+    Some(Account { owner, subaccount })
+}
+
+impl TransferLedger for IcrcLedger {
+    async fn transfer(&self, ledger: Principal, args: TransferArg) -> CanisterResult<u64> {
+        let (result,): (Result<Nat, TransferError>,) =
+            ic_cdk::call(ledger, "icrc1_transfer", (args,))
+                .await
+                .map_err(|e| {
+                    ApiError::unexpected()
+                        .add_message("Failed to call ledger")
+                        .add_info(format!("Canister: {ledger} error: {:?}", e))
+                        .add_method_name("icrc1_transfer")
+                })?;
+
+        let blockheight = result.map_err(|e| {
+            ApiError::bad_request()
+                .add_message("Transfer failed")
+                .add_info(format!("Canister: {ledger} error: {:?}", e))
+                .add_method_name("icrc1_transfer")
+        })?;
+
+        nat_to_blockheight(blockheight, "icrc1_transfer")
+    }
+
+    async fn transfer_from(
+        &self,
+        ledger: Principal,
+        args: TransferFromArgs,
+    ) -> CanisterResult<u64> {
+        let (result,): (Result<Nat, TransferFromError>,) =
+            ic_cdk::call(ledger, "icrc2_transfer_from", (args,))
+                .await
+                .map_err(|e| {
+                    ApiError::unexpected()
+                        .add_message("Failed to call ledger")
+                        .add_info(format!("Canister: {ledger} error: {:?}", e))
+                        .add_method_name("icrc2_transfer_from")
+                })?;
+
+        let blockheight = result.map_err(|e| {
+            ApiError::bad_request()
+                .add_message("Transfer from failed")
+                .add_info(format!("Canister: {ledger} error: {:?}", e))
+                .add_method_name("icrc2_transfer_from")
+        })?;
+
+        nat_to_blockheight(blockheight, "icrc2_transfer_from")
+    }
+}
+
+fn nat_to_blockheight(blockheight: Nat, method_name: &str) -> CanisterResult<u64> {
+    u64::try_from(&blockheight.0).map_err(|_| {
+        ApiError::unexpected()
+            .add_message("Block height does not fit in u64")
+            .add_info(blockheight)
+            .add_method_name(method_name)
+    })
+}
+
+/// Checks that the transfer is sent by the principal to the receiver and carries the memo
+pub fn validate_transfer(
+    transfer: &BlockTransfer,
+    sender: Principal,
+    receiver: &Account,
+    memo: &Memo,
+) -> CanisterResult<()> {
+    if !transfer.from.is_owned_by(sender) {
+        return Err(ApiError::unauthorized().add_message("Transfer is not sent by the caller"));
+    }
+
+    if !transfer.to.is_account(receiver) {
+        return Err(ApiError::bad_request().add_message("Transfer is not sent to the receiver"));
+    }
+
+    if transfer.memo.as_ref() != Some(memo) {
+        return Err(ApiError::bad_request().add_message("Transfer memo does not match"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::{Decode, Encode};
+
+    use super::*;
+
+    // the full `query_blocks` block as the ICP ledger encodes it
+    #[derive(CandidType)]
+    struct FullIcpBlock {
+        parent_hash: Option<ByteBuf>,
+        transaction: FullIcpTransaction,
+        timestamp: IcpTimestamp,
+    }
+
+    #[derive(CandidType)]
+    struct FullIcpTransaction {
+        memo: u64,
+        icrc1_memo: Option<ByteBuf>,
+        operation: Option<FullIcpOperation>,
+        created_at_time: IcpTimestamp,
+    }
+
+    #[derive(CandidType)]
+    struct IcpTimestamp {
+        timestamp_nanos: u64,
+    }
+
+    #[derive(CandidType)]
+    #[allow(dead_code)]
+    enum FullIcpOperation {
+        Transfer {
+            from: ByteBuf,
+            to: ByteBuf,
+            amount: IcpTokens,
+            fee: IcpTokens,
+            spender: Option<ByteBuf>,
+        },
+        Mint {
+            to: ByteBuf,
+            amount: IcpTokens,
+        },
+    }
+
+    #[test]
+    fn account_identifier_of_the_anonymous_principal() {
+        let identifier = account_identifier(&Account::from(Principal::anonymous()));
+        let hex: String = identifier
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        assert_eq!(
+            hex,
+            "1c7a48ba6a562aa9eaa2481a9049cdf0433b9738c992d698c31d8abf89cadc79"
+        );
+    }
+
+    #[test]
+    fn decodes_icp_blocks() {
+        let from = ByteBuf::from(account_identifier(&Account::from(Principal::anonymous())));
+        let block = FullIcpBlock {
+            parent_hash: None,
+            transaction: FullIcpTransaction {
+                memo: 0,
+                icrc1_memo: Some(ByteBuf::from(b"memo".to_vec())),
+                operation: Some(FullIcpOperation::Transfer {
+                    from: from.clone(),
+                    to: ByteBuf::from(vec![0; 32]),
+                    amount: IcpTokens { e8s: 100 },
+                    fee: IcpTokens { e8s: 10 },
+                    spender: None,
+                }),
+                created_at_time: IcpTimestamp { timestamp_nanos: 0 },
+            },
+            timestamp: IcpTimestamp { timestamp_nanos: 0 },
+        };
+
+        let decoded = Decode!(&Encode!(&block).unwrap(), IcpBlock).unwrap();
+        let Some(IcpOperation::Transfer {
+            from: decoded_from,
+            amount,
+            ..
+        }) = decoded.transaction.operation
+        else {
+            panic!("Expected a transfer");
+        };
+        assert_eq!(decoded_from, from);
+        assert_eq!(amount.e8s, 100);
+
+        let mint = FullIcpOperation::Mint {
+            to: from,
+            amount: IcpTokens { e8s: 1 },
+        };
+        let decoded = Decode!(&Encode!(&mint).unwrap(), IcpOperation).unwrap();
+        assert!(matches!(decoded, IcpOperation::Mint(_)));
+    }
+
+    #[test]
+    fn reads_icrc3_transfer_blocks() {
+        let owner = Principal::from_slice(&[1, 2, 3]);
+        let account = Value::Array(vec![Value::blob(owner.as_slice())]);
+        let block = Value::map([
+            ("btype", Value::text("1xfer")),
+            (
+                "tx",
+                Value::map([
+                    ("from", account.clone()),
+                    ("to", account),
+                    ("amt", Value::Nat(Nat::from(500u64))),
+                    ("memo", Value::blob(b"memo".to_vec())),
+                ]),
+            ),
+        ]);
+
+        let transfer = icrc3_block_to_transfer(&block).unwrap();
+        assert!(transfer.from.is_owned_by(owner));
+        assert!(transfer.to.is_account(&Account::from(owner)));
+        assert_eq!(transfer.amount, Nat::from(500u64));
+        assert_eq!(transfer.memo, Some(Memo::from(b"memo".to_vec())));
+    }
+
+    #[test]
+    fn decodes_icrc3_block_results() {
+        let result = GetBlocksResult {
+            blocks: vec![BlockWithId {
+                id: Nat::from(7u64),
+                block: Value::map([("btype", Value::text("1xfer"))]),
+            }],
+            archived_blocks: vec![],
+        };
+
+        let decoded = Decode!(&Encode!(&result).unwrap(), GetBlocksResult).unwrap();
+        assert_eq!(decoded.blocks[0].id, Nat::from(7u64));
+    }
+}
@@ -1,7 +1,14 @@
+pub mod boost_payment;
 pub mod candid;
+pub mod geo;
 pub mod guards;
 pub mod ic_call;
+pub mod ical;
+pub mod ledger;
+pub mod neuron_gate;
 pub mod state;
 pub mod str;
+pub mod ticket_payment;
 pub mod time_helper;
+pub mod token_gate;
 pub mod validator;
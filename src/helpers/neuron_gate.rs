@@ -0,0 +1,457 @@
+use std::future::Future;
+
+use candid::Principal;
+
+use crate::{
+    api_error::ApiError,
+    gate::{GateFailure, GateMode, GateResult},
+    neuron::{DissolveState, ListNeurons, ListNeuronsResponse, Neuron},
+    privacy::{NeuronGated, NeuronGatedRules},
+    CanisterResult,
+};
+
+pub const LIST_NEURONS_PAGE_SIZE: u32 = 100;
+
+// guards against governance canisters that keep returning the same page
+const LIST_NEURONS_MAX_PAGES: usize = 50;
+
+const NANOSECONDS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Source of neurons, implemented by `SnsGovernance` for the real governance canisters and by stand-ins in tests
+pub trait NeuronGovernance {
+    fn list_neurons(
+        &self,
+        governance: Principal,
+        args: ListNeurons,
+    ) -> impl Future<Output = CanisterResult<ListNeuronsResponse>>;
+}
+
+pub struct SnsGovernance;
+
+impl NeuronGovernance for SnsGovernance {
+    async fn list_neurons(
+        &self,
+        governance: Principal,
+        args: ListNeurons,
+    ) -> CanisterResult<ListNeuronsResponse> {
+        let (response,): (ListNeuronsResponse,) = ic_cdk::call(governance, "list_neurons", (args,))
+            .await
+            .map_err(|e| {
+                ApiError::unexpected()
+                    .add_message("Failed to call governance canister")
+                    .add_info(format!("Canister: {governance} error: {:?}", e))
+                    .add_method_name("list_neurons")
+            })?;
+
+        Ok(response)
+    }
+}
+
+pub async fn check_neuron_gate(
+    principal: Principal,
+    gates: &[NeuronGated],
+    mode: GateMode,
+    now: u64,
+) -> CanisterResult<GateResult> {
+    check_neuron_gate_with(&SnsGovernance, principal, gates, mode, now).await
+}
+
+/// Lists the neurons of the principal on each gate's governance canister and evaluates the gate rules.
+/// With `GateMode::AnyOf` the governance canisters after the first met requirement are not called and a
+/// governance canister that can not be reached counts as a failed requirement, with `GateMode::AllOf` its
+/// error is returned.
+pub async fn check_neuron_gate_with<G: NeuronGovernance>(
+    governance: &G,
+    principal: Principal,
+    gates: &[NeuronGated],
+    mode: GateMode,
+    now: u64,
+) -> CanisterResult<GateResult> {
+    let mut failed: Vec<GateFailure> = vec![];
+
+    for gate in gates {
+        let neurons = match list_all_neurons(governance, gate.governance_canister, principal).await
+        {
+            Err(err) if mode == GateMode::AnyOf => {
+                failed.push(GateFailure::new(&gate.name, err.to_string()));
+                continue;
+            }
+            result => result.map_err(|err| err.add_info(format!("Neuron gate: {}", gate.name)))?,
+        };
+
+        let result = evaluate_neuron_rules(&neurons, &gate.rules, now);
+        match result.is_passed {
+            true if mode == GateMode::AnyOf => {
+                return Ok(GateResult::new(mode, gates.len(), vec![]))
+            }
+            true => {}
+            false => failed.push(GateFailure::new(
+                &gate.name,
+                result
+                    .failed
+                    .iter()
+                    .map(|failure| failure.reason.clone())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            )),
+        }
+    }
+
+    Ok(GateResult::new(mode, gates.len(), failed))
+}
+
+/// Pages through `list_neurons` until the governance canister returns a partial page
+pub async fn list_all_neurons<G: NeuronGovernance>(
+    governance: &G,
+    governance_canister: Principal,
+    principal: Principal,
+) -> CanisterResult<Vec<Neuron>> {
+    let mut neurons: Vec<Neuron> = vec![];
+    let mut start_page_at = None;
+
+    for _ in 0..LIST_NEURONS_MAX_PAGES {
+        let response = governance
+            .list_neurons(
+                governance_canister,
+                ListNeurons {
+                    of_principal: Some(principal),
+                    limit: LIST_NEURONS_PAGE_SIZE,
+                    start_page_at,
+                },
+            )
+            .await?;
+
+        let is_last_page = response.neurons.len() < LIST_NEURONS_PAGE_SIZE as usize;
+        start_page_at = response.neurons.last().and_then(|neuron| neuron.id.clone());
+        neurons.extend(response.neurons);
+
+        if is_last_page || start_page_at.is_none() {
+            break;
+        }
+    }
+
+    Ok(neurons)
+}
+
+/// Passes when a single neuron meets all rules, `now` is in nanoseconds.
+/// When no neuron passes, the failures of the neuron that came closest are returned.
+pub fn evaluate_neuron_rules(
+    neurons: &[Neuron],
+    rules: &[NeuronGatedRules],
+    now: u64,
+) -> GateResult {
+    let now_seconds = now / NANOSECONDS_PER_SECOND;
+
+    let closest = neurons
+        .iter()
+        .map(|neuron| {
+            rules
+                .iter()
+                .filter_map(|rule| check_neuron_rule(neuron, rule, now_seconds))
+                .collect::<Vec<GateFailure>>()
+        })
+        .min_by_key(|failed| failed.len());
+
+    match closest {
+        Some(failed) => GateResult::new(GateMode::AllOf, rules.len(), failed),
+        None => GateResult {
+            is_passed: false,
+            failed: vec![GateFailure::new("Neuron", "No neurons found")],
+        },
+    }
+}
+
+fn check_neuron_rule(
+    neuron: &Neuron,
+    rule: &NeuronGatedRules,
+    now_seconds: u64,
+) -> Option<GateFailure> {
+    use NeuronGatedRules::*;
+    match rule {
+        MinAge(min_age) => {
+            let age = neuron_age_seconds(neuron, now_seconds);
+            (age < *min_age).then(|| {
+                GateFailure::new(
+                    "MinAge",
+                    format!("Neuron age of {age} seconds is below {min_age} seconds"),
+                )
+            })
+        }
+        MinStake(min_stake) => {
+            let stake = neuron_stake_e8s(neuron);
+            (stake < *min_stake).then(|| {
+                GateFailure::new(
+                    "MinStake",
+                    format!("Neuron stake of {stake} e8s is below {min_stake} e8s"),
+                )
+            })
+        }
+        MinDissolveDelay(min_delay) => {
+            let delay = neuron_dissolve_delay_seconds(neuron, now_seconds);
+            (delay < *min_delay).then(|| {
+                GateFailure::new(
+                    "MinDissolveDelay",
+                    format!("Dissolve delay of {delay} seconds is below {min_delay} seconds"),
+                )
+            })
+        }
+        IsDisolving(is_dissolving) => (is_neuron_dissolving(neuron, now_seconds) != *is_dissolving)
+            .then(|| {
+                GateFailure::new(
+                    "IsDisolving",
+                    match is_dissolving {
+                        true => "Neuron is not dissolving",
+                        false => "Neuron is dissolving",
+                    },
+                )
+            }),
+    }
+}
+
+/// Dissolving neurons do not age, governance marks them with an `aging_since_timestamp_seconds` of `u64::MAX`
+pub fn neuron_age_seconds(neuron: &Neuron, now_seconds: u64) -> u64 {
+    now_seconds.saturating_sub(neuron.aging_since_timestamp_seconds)
+}
+
+pub fn neuron_stake_e8s(neuron: &Neuron) -> u64 {
+    neuron
+        .cached_neuron_stake_e8s
+        .saturating_sub(neuron.neuron_fees_e8s)
+        .saturating_add(neuron.staked_maturity_e8s_equivalent.unwrap_or_default())
+}
+
+pub fn neuron_dissolve_delay_seconds(neuron: &Neuron, now_seconds: u64) -> u64 {
+    match neuron.dissolve_state {
+        Some(DissolveState::DissolveDelaySeconds(delay)) => delay,
+        Some(DissolveState::WhenDissolvedTimestampSeconds(dissolved_at)) => {
+            dissolved_at.saturating_sub(now_seconds)
+        }
+        None => 0,
+    }
+}
+
+pub fn is_neuron_dissolving(neuron: &Neuron, now_seconds: u64) -> bool {
+    matches!(
+        neuron.dissolve_state,
+        Some(DissolveState::WhenDissolvedTimestampSeconds(dissolved_at)) if dissolved_at > now_seconds
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::RefCell,
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use crate::neuron::NeuronId;
+
+    use super::*;
+
+    const NOW: u64 = 1_000_000 * NANOSECONDS_PER_SECOND;
+
+    /// Stand-in governance with the neurons per governance canister, the other canisters fail.
+    /// The `start_page_at` of every call is recorded.
+    struct StandInGovernance {
+        neurons: Vec<(Principal, Vec<Neuron>)>,
+        calls: RefCell<Vec<Option<Vec<u8>>>>,
+    }
+
+    impl StandInGovernance {
+        fn new(neurons: Vec<(Principal, Vec<Neuron>)>) -> Self {
+            Self {
+                neurons,
+                calls: RefCell::new(vec![]),
+            }
+        }
+    }
+
+    impl NeuronGovernance for StandInGovernance {
+        async fn list_neurons(
+            &self,
+            governance: Principal,
+            args: ListNeurons,
+        ) -> CanisterResult<ListNeuronsResponse> {
+            self.calls
+                .borrow_mut()
+                .push(args.start_page_at.as_ref().map(|id| id.id.clone()));
+
+            let neurons = self
+                .neurons
+                .iter()
+                .find(|(principal, _)| *principal == governance)
+                .map(|(_, neurons)| neurons)
+                .ok_or_else(|| ApiError::unexpected().add_message("Governance unavailable"))?;
+
+            let start = match args.start_page_at {
+                Some(start_page_at) => {
+                    neurons
+                        .iter()
+                        .position(|neuron| {
+                            neuron.id.as_ref().map(|id| &id.id) == Some(&start_page_at.id)
+                        })
+                        .unwrap_or(neurons.len())
+                        + 1
+                }
+                None => 0,
+            };
+
+            Ok(ListNeuronsResponse {
+                neurons: neurons
+                    .iter()
+                    .skip(start)
+                    .take(args.limit as usize)
+                    .cloned()
+                    .collect(),
+            })
+        }
+    }
+
+    // the stand-in never suspends, so a single poll completes the future
+    fn block_on<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("The stand-in governance should not suspend"),
+        }
+    }
+
+    fn neuron(id: u32, stake_e8s: u64, age_seconds: u64) -> Neuron {
+        Neuron {
+            id: Some(NeuronId {
+                id: id.to_be_bytes().to_vec(),
+            }),
+            staked_maturity_e8s_equivalent: None,
+            permissions: vec![],
+            maturity_e8s_equivalent: 0,
+            cached_neuron_stake_e8s: stake_e8s,
+            created_timestamp_seconds: 0,
+            source_nns_neuron_id: None,
+            auto_stake_maturity: None,
+            aging_since_timestamp_seconds: NOW / NANOSECONDS_PER_SECOND - age_seconds,
+            dissolve_state: Some(DissolveState::DissolveDelaySeconds(0)),
+            voting_power_percentage_multiplier: 100,
+            vesting_period_seconds: None,
+            disburse_maturity_in_progress: vec![],
+            followees: vec![],
+            neuron_fees_e8s: 0,
+        }
+    }
+
+    fn gate(name: &str, governance: Principal, rules: Vec<NeuronGatedRules>) -> NeuronGated {
+        NeuronGated {
+            name: name.to_string(),
+            description: String::new(),
+            governance_canister: governance,
+            ledger_canister: Principal::anonymous(),
+            rules,
+        }
+    }
+
+    #[test]
+    fn a_single_neuron_has_to_meet_all_rules() {
+        let rules = [
+            NeuronGatedRules::MinStake(100),
+            NeuronGatedRules::MinAge(60),
+        ];
+        // one neuron has the stake, the other the age
+        let neurons = [neuron(0, 100, 0), neuron(1, 0, 60)];
+
+        let result = evaluate_neuron_rules(&neurons, &rules, NOW);
+        assert!(!result.is_passed);
+        assert_eq!(result.failed.len(), 1);
+
+        let result = evaluate_neuron_rules(&[neuron(2, 150, 90)], &rules, NOW);
+        assert!(result.is_passed);
+        assert!(result.failed.is_empty());
+    }
+
+    #[test]
+    fn no_neurons_fail_the_rules() {
+        let result = evaluate_neuron_rules(&[], &[NeuronGatedRules::MinStake(1)], NOW);
+
+        assert!(!result.is_passed);
+        assert_eq!(result.failed[0].reason, "No neurons found");
+    }
+
+    #[test]
+    fn list_all_neurons_pages_until_a_partial_page() {
+        let canister = Principal::from_slice(&[1]);
+        let neurons: Vec<Neuron> = (0..250).map(|id| neuron(id, 1, 0)).collect();
+        let governance = StandInGovernance::new(vec![(canister, neurons)]);
+
+        let listed = block_on(list_all_neurons(
+            &governance,
+            canister,
+            Principal::anonymous(),
+        ))
+        .unwrap();
+
+        assert_eq!(listed.len(), 250);
+        let calls = governance.calls.borrow();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0], None);
+        assert_eq!(calls[1], listed[99].id.as_ref().map(|id| id.id.clone()));
+        assert_eq!(calls[2], listed[199].id.as_ref().map(|id| id.id.clone()));
+    }
+
+    #[test]
+    fn list_all_neurons_stops_after_the_last_full_page() {
+        let canister = Principal::from_slice(&[1]);
+        let neurons: Vec<Neuron> = (0..200).map(|id| neuron(id, 1, 0)).collect();
+        let governance = StandInGovernance::new(vec![(canister, neurons)]);
+
+        let listed = block_on(list_all_neurons(
+            &governance,
+            canister,
+            Principal::anonymous(),
+        ))
+        .unwrap();
+
+        // the third call returns an empty page
+        assert_eq!(listed.len(), 200);
+        assert_eq!(governance.calls.borrow().len(), 3);
+    }
+
+    #[test]
+    fn any_of_records_governance_errors_as_failures() {
+        let available = Principal::from_slice(&[1]);
+        let unavailable = Principal::from_slice(&[2]);
+        let governance = StandInGovernance::new(vec![(available, vec![neuron(0, 100, 0)])]);
+        let gates = [
+            gate("down", unavailable, vec![NeuronGatedRules::MinStake(1)]),
+            gate("up", available, vec![NeuronGatedRules::MinStake(50)]),
+        ];
+
+        let result = block_on(check_neuron_gate_with(
+            &governance,
+            Principal::anonymous(),
+            &gates,
+            GateMode::AnyOf,
+            NOW,
+        ))
+        .unwrap();
+        assert!(result.is_passed);
+
+        let result = block_on(check_neuron_gate_with(
+            &governance,
+            Principal::anonymous(),
+            &gates[..1],
+            GateMode::AnyOf,
+            NOW,
+        ))
+        .unwrap();
+        assert!(!result.is_passed);
+        assert_eq!(result.failed[0].name, "down");
+
+        let result = block_on(check_neuron_gate_with(
+            &governance,
+            Principal::anonymous(),
+            &gates,
+            GateMode::AllOf,
+            NOW,
+        ));
+        assert!(result.is_err());
+    }
+}
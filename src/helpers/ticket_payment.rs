@@ -0,0 +1,177 @@
+use candid::{Nat, Principal};
+use icrc_ledger_types::{
+    icrc1::{
+        account::Account,
+        transfer::{Memo, TransferArg},
+    },
+    icrc2::transfer_from::TransferFromArgs,
+};
+
+use crate::{
+    api_error::ApiError,
+    helpers::ledger::{validate_transfer, IcrcLedger, TransactionLedger, TransferLedger},
+    ticket::{Ticket, TicketType},
+    CanisterResult,
+};
+
+// A ticket purchase takes these steps, the event has to be loaded again after every call to the ledger
+// because other calls can change it while the canister waits for the ledger:
+// 1. `EventWithAttendees::guard_ticket_purchase` to check the ticket type before any tokens are moved
+// 2. `collect_ticket_payment` or `verify_ticket_payment` to get the block height of the payment, both carry the
+//    `ticket_memo` of the event
+// 3. `EventWithAttendees::issue_ticket` on the reloaded event, when that fails the payment is sent back
+//    with `refund_payment`
+// 4. `Ticket::purchase_notification` for the buyer
+//
+// Canceling the event marks the tickets for a refund, `refund_ticket` sends the price back and the
+// block height is recorded with `EventWithAttendees::mark_ticket_refunded`.
+
+/// The memo a ticket payment has to carry, it keeps a block from paying for tickets of other events
+pub fn ticket_memo(event_id: u64) -> Memo {
+    Memo::from(format!("ticket:{event_id}").into_bytes())
+}
+
+pub async fn collect_ticket_payment(
+    event_id: u64,
+    ticket_type: &TicketType,
+    buyer: Principal,
+    treasury: Account,
+) -> CanisterResult<u64> {
+    collect_ticket_payment_with(&IcrcLedger, event_id, ticket_type, buyer, treasury).await
+}
+
+/// Pulls the ticket price from the default account of the buyer with an ICRC-2 approval and returns the block height
+pub async fn collect_ticket_payment_with<L: TransferLedger>(
+    ledger: &L,
+    event_id: u64,
+    ticket_type: &TicketType,
+    buyer: Principal,
+    treasury: Account,
+) -> CanisterResult<u64> {
+    let args = TransferFromArgs {
+        spender_subaccount: None,
+        from: Account::from(buyer),
+        to: treasury,
+        amount: Nat::from(ticket_type.price),
+        fee: None,
+        memo: Some(ticket_memo(event_id)),
+        created_at_time: None,
+    };
+
+    ledger
+        .transfer_from(ticket_type.ledger, args)
+        .await
+        .map_err(|err| {
+            err.add_method_name("collect_ticket_payment")
+                .add_info(format!("Ticket type: {}", ticket_type.id))
+        })
+}
+
+pub async fn verify_ticket_payment(
+    event_id: u64,
+    ticket_type: &TicketType,
+    blockheight: u64,
+    buyer: Principal,
+    treasury: Account,
+) -> CanisterResult<u64> {
+    verify_ticket_payment_with(
+        &IcrcLedger,
+        event_id,
+        ticket_type,
+        blockheight,
+        buyer,
+        treasury,
+    )
+    .await
+}
+
+/// Verifies that the block is a transfer of at least the ticket price from the buyer to the treasury with the
+/// `ticket_memo` of the event. `EventWithAttendees::issue_ticket` refuses blocks that already paid for a ticket
+/// of the event, the memo keeps a block from paying for tickets of other events.
+pub async fn verify_ticket_payment_with<L: TransactionLedger>(
+    ledger: &L,
+    event_id: u64,
+    ticket_type: &TicketType,
+    blockheight: u64,
+    buyer: Principal,
+    treasury: Account,
+) -> CanisterResult<u64> {
+    let transfer = ledger
+        .get_transfer(ticket_type.ledger, blockheight)
+        .await?
+        .ok_or_else(|| {
+            ApiError::not_found()
+                .add_method_name("verify_ticket_payment")
+                .add_message("Transaction not found")
+                .add_info(blockheight)
+        })?;
+
+    validate_transfer(&transfer, buyer, &treasury, &ticket_memo(event_id)).map_err(|err| {
+        err.add_method_name("verify_ticket_payment")
+            .add_info(blockheight)
+    })?;
+
+    if transfer.amount < ticket_type.price {
+        return Err(ApiError::bad_request()
+            .add_method_name("verify_ticket_payment")
+            .add_message("Transferred amount is below the ticket price")
+            .add_info(&transfer.amount));
+    }
+
+    Ok(blockheight)
+}
+
+pub async fn refund_ticket(ticket: &Ticket, treasury: Account) -> CanisterResult<u64> {
+    refund_ticket_with(&IcrcLedger, ticket, treasury).await
+}
+
+/// Sends the price of a ticket with a pending refund back to the owner and returns the block height
+pub async fn refund_ticket_with<L: TransferLedger>(
+    ledger: &L,
+    ticket: &Ticket,
+    treasury: Account,
+) -> CanisterResult<u64> {
+    if !ticket.is_refund_pending() {
+        return Err(ApiError::bad_request()
+            .add_method_name("refund_ticket")
+            .add_message("Ticket has no pending refund")
+            .add_info(ticket.id));
+    }
+
+    refund_payment_with(ledger, ticket.ledger, treasury, ticket.owner, ticket.price)
+        .await
+        .map_err(|err| err.add_method_name("refund_ticket"))
+}
+
+pub async fn refund_payment(
+    ledger: Principal,
+    treasury: Account,
+    to: Principal,
+    amount: u64,
+) -> CanisterResult<u64> {
+    refund_payment_with(&IcrcLedger, ledger, treasury, to, amount).await
+}
+
+/// Sends `amount` from the treasury back to the default account of `to`, the treasury has to be owned
+/// by the canister and pays the ledger fee
+pub async fn refund_payment_with<L: TransferLedger>(
+    transfer_ledger: &L,
+    ledger: Principal,
+    treasury: Account,
+    to: Principal,
+    amount: u64,
+) -> CanisterResult<u64> {
+    let args = TransferArg {
+        from_subaccount: treasury.subaccount,
+        to: Account::from(to),
+        fee: None,
+        created_at_time: None,
+        memo: None,
+        amount: Nat::from(amount),
+    };
+
+    transfer_ledger
+        .transfer(ledger, args)
+        .await
+        .map_err(|err| err.add_method_name("refund_payment"))
+}
@@ -1,3 +1,6 @@
+use chrono::{DateTime, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+
 pub fn hours_to_nanoseconds(hours: u64) -> u64 {
     let minutes_per_hour = 60;
     let seconds_per_minute = 60;
@@ -5,3 +8,271 @@ pub fn hours_to_nanoseconds(hours: u64) -> u64 {
 
     hours * minutes_per_hour * seconds_per_minute * nanoseconds_per_second
 }
+
+/// The canister time in nanoseconds, unit tests run outside of a canister and read `0`
+pub fn time() -> u64 {
+    if cfg!(test) {
+        0
+    } else {
+        ic_cdk::api::time()
+    }
+}
+
+pub const NANOSECONDS_PER_SECOND: u64 = 1_000_000_000;
+pub const NANOSECONDS_PER_DAY: u64 = 24 * 60 * 60 * NANOSECONDS_PER_SECOND;
+
+/// Days since 1970-01-01 for the given proleptic gregorian date, `month` and `day` start at 1
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The `(year, month, day)` for the given days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Weekday of the given days since 1970-01-01 where monday is `0` and sunday is `6`
+pub fn weekday_from_days(days: i64) -> u32 {
+    // 1970-01-01 was a thursday
+    (days + 3).rem_euclid(7) as u32
+}
+
+pub fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Splits a nanosecond timestamp into days since 1970-01-01 and the nanoseconds into that day
+pub fn split_nanoseconds(timestamp: u64) -> (i64, u64) {
+    (
+        (timestamp / NANOSECONDS_PER_DAY) as i64,
+        timestamp % NANOSECONDS_PER_DAY,
+    )
+}
+
+/// Nanosecond timestamp for the given days since 1970-01-01, dates before 1970 are clamped to `0`
+pub fn days_to_nanoseconds(days: i64) -> u64 {
+    (days.max(0) as u64).saturating_mul(NANOSECONDS_PER_DAY)
+}
+
+/// Formats a nanosecond timestamp as an ISO-8601 UTC date-time like `2024-01-31T10:00:00Z`,
+/// milliseconds are only added when present
+pub fn format_iso8601(timestamp: u64) -> String {
+    let (days, nanoseconds) = split_nanoseconds(timestamp);
+    let (year, month, day) = civil_from_days(days);
+    let seconds = nanoseconds / NANOSECONDS_PER_SECOND;
+    let milliseconds = nanoseconds % NANOSECONDS_PER_SECOND / 1_000_000;
+
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    );
+
+    match milliseconds {
+        0 => format!("{year:04}-{month:02}-{day:02}T{time}Z"),
+        _ => format!("{year:04}-{month:02}-{day:02}T{time}.{milliseconds:03}Z"),
+    }
+}
+
+/// Parses an ISO-8601 date (`2024-01-31`) or date-time (`2024-01-31T10:00:00.000+01:00`) into a nanosecond
+/// timestamp, date-times without an offset are read as UTC
+pub fn parse_iso8601(value: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid ISO-8601 date: {value}");
+    let value = value.trim();
+
+    let (date, time) = value.split_once(['T', 't', ' ']).unwrap_or((value, ""));
+
+    let date_parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = date_parts.as_slice() else {
+        return Err(invalid());
+    };
+    let (year, month, day): (i64, u32, u32) = (
+        year.parse().map_err(|_| invalid())?,
+        month.parse().map_err(|_| invalid())?,
+        day.parse().map_err(|_| invalid())?,
+    );
+
+    if year < 1970 || !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+
+    let (time, offset_seconds) = match time.strip_suffix(['Z', 'z']) {
+        Some(time) => (time, 0),
+        None => match time.rfind(['+', '-']) {
+            Some(index) => (
+                &time[..index],
+                parse_utc_offset(&time[index..]).ok_or_else(invalid)?,
+            ),
+            None => (time, 0),
+        },
+    };
+
+    let mut nanoseconds_of_day = 0u64;
+    if !time.is_empty() {
+        let (time, fraction) = time.split_once(['.', ',']).unwrap_or((time, ""));
+        let time_parts: Vec<u64> = time
+            .split(':')
+            .map(|part| part.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+
+        let (hours, minutes, seconds) = match time_parts.as_slice() {
+            [hours, minutes] => (*hours, *minutes, 0),
+            [hours, minutes, seconds] => (*hours, *minutes, *seconds),
+            _ => return Err(invalid()),
+        };
+
+        if hours > 23 || minutes > 59 || seconds > 60 {
+            return Err(invalid());
+        }
+
+        // only the first 9 digits of the fraction fit in nanoseconds
+        let fraction: String = fraction.chars().take(9).collect();
+        let fraction_nanoseconds = match fraction.is_empty() {
+            true => 0,
+            false => {
+                fraction.parse::<u64>().map_err(|_| invalid())?
+                    * 10u64.pow(9 - fraction.len() as u32)
+            }
+        };
+
+        nanoseconds_of_day =
+            (hours * 3600 + minutes * 60 + seconds) * NANOSECONDS_PER_SECOND + fraction_nanoseconds;
+    }
+
+    let local = days_to_nanoseconds(days_from_civil(year, month, day)) + nanoseconds_of_day;
+    let utc = local as i128 - offset_seconds as i128 * NANOSECONDS_PER_SECOND as i128;
+    u64::try_from(utc).map_err(|_| invalid())
+}
+
+/// The offset in seconds of a fixed-offset timezone like `UTC`, `+02:00`, `-0530` or `Etc/GMT-2`.
+/// Returns `None` for region based IANA ids like `Europe/Amsterdam`, those are resolved with `utc_offset_at`.
+pub fn parse_utc_offset(timezone: &str) -> Option<i64> {
+    let timezone = timezone.trim();
+    if ["UTC", "GMT", "Z", "Etc/UTC", "Etc/GMT"].contains(&timezone) {
+        return Some(0);
+    }
+
+    // the sign of the `Etc/GMT` zones is inverted, `Etc/GMT-2` is two hours ahead of UTC
+    if let Some(hours) = timezone.strip_prefix("Etc/GMT") {
+        let hours: i64 = hours.parse().ok()?;
+        return (-14..=12).contains(&hours).then_some(-hours * 3600);
+    }
+
+    let offset = timezone
+        .strip_prefix("UTC")
+        .or_else(|| timezone.strip_prefix("GMT"))
+        .unwrap_or(timezone);
+    let (sign, offset) = match offset.split_at_checked(1)? {
+        ("+", offset) => (1, offset),
+        ("-", offset) => (-1, offset),
+        _ => return None,
+    };
+
+    let digits = offset.replace(':', "");
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let (hours, minutes) = match digits.len() {
+        1 | 2 => (digits.parse::<i64>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i64>().ok()?,
+            digits[2..].parse::<i64>().ok()?,
+        ),
+        _ => return None,
+    };
+
+    (hours <= 14 && minutes < 60).then_some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Formats an offset in seconds like `+02:00`, the inverse of `parse_utc_offset`
+pub fn format_utc_offset(offset_seconds: i64) -> String {
+    let sign = if offset_seconds < 0 { '-' } else { '+' };
+    let minutes = offset_seconds.abs() / 60;
+    format!("{sign}{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Fixed offsets and the IANA ids of the timezone database like `Europe/Amsterdam` are accepted
+pub fn is_valid_timezone(timezone: &str) -> bool {
+    parse_utc_offset(timezone).is_some() || timezone.trim().parse::<Tz>().is_ok()
+}
+
+/// The offset in seconds of the timezone at the UTC `timestamp`, IANA ids follow their daylight saving time.
+/// Returns `None` for unknown timezones.
+pub fn utc_offset_at(timezone: &str, timestamp: u64) -> Option<i64> {
+    if let Some(offset) = parse_utc_offset(timezone) {
+        return Some(offset);
+    }
+
+    let timezone: Tz = timezone.trim().parse().ok()?;
+    let utc = to_naive_date_time(timestamp)?;
+    Some(
+        timezone
+            .offset_from_utc_datetime(&utc)
+            .fix()
+            .local_minus_utc() as i64,
+    )
+}
+
+/// The UTC timestamp of the `local` date and time in the timezone, the inverse of `utc_offset_at`.
+/// An ambiguous local time resolves to its first occurrence and a local time skipped by a daylight saving
+/// time change is moved forward by the change. Returns `None` for unknown timezones.
+pub fn local_to_utc_at(timezone: &str, local: u64) -> Option<u64> {
+    let offset = match parse_utc_offset(timezone) {
+        Some(offset) => offset,
+        None => {
+            let tz: Tz = timezone.trim().parse().ok()?;
+            match tz.offset_from_local_datetime(&to_naive_date_time(local)?) {
+                LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => {
+                    offset.fix().local_minus_utc() as i64
+                }
+                // the offset from before the gap, changes are never less than a day apart
+                LocalResult::None => {
+                    utc_offset_at(timezone, local.saturating_sub(NANOSECONDS_PER_DAY))?
+                }
+            }
+        }
+    };
+
+    u64::try_from(local as i128 - offset as i128 * NANOSECONDS_PER_SECOND as i128).ok()
+}
+
+fn to_naive_date_time(timestamp: u64) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp(
+        (timestamp / NANOSECONDS_PER_SECOND) as i64,
+        (timestamp % NANOSECONDS_PER_SECOND) as u32,
+    )
+    .map(|date_time| date_time.naive_utc())
+}
@@ -0,0 +1,211 @@
+use candid::Principal;
+use icrc_ledger_types::icrc1::account::Account;
+
+use crate::{
+    gate::{GateFailure, GateMode, GateResult},
+    helpers::{
+        ledger::{IcrcLedger, TokenLedger},
+        str::eq_str,
+    },
+    privacy::TokenGated,
+    CanisterResult,
+};
+
+/// The token standards that expose `icrc1_balance_of`
+pub const SUPPORTED_TOKEN_STANDARDS: [&str; 3] = ["icrc1", "icrc2", "icrc3"];
+
+pub async fn check_token_gate(
+    principal: Principal,
+    gates: &[TokenGated],
+    mode: GateMode,
+) -> CanisterResult<GateResult> {
+    check_token_gate_with(&IcrcLedger, principal, gates, mode).await
+}
+
+/// Checks the balances of the principal's default account against the token requirements.
+/// With `GateMode::AnyOf` the ledgers after the first met requirement are not called and a ledger
+/// that can not be reached counts as a failed requirement, with `GateMode::AllOf` its error is returned.
+pub async fn check_token_gate_with<L: TokenLedger>(
+    ledger: &L,
+    principal: Principal,
+    gates: &[TokenGated],
+    mode: GateMode,
+) -> CanisterResult<GateResult> {
+    let mut failed: Vec<GateFailure> = vec![];
+
+    for gate in gates {
+        let result = match check_token_requirement(ledger, principal, gate).await {
+            Err(err) if mode == GateMode::AnyOf => {
+                Some(GateFailure::new(&gate.name, err.to_string()))
+            }
+            result => result?,
+        };
+
+        match result {
+            Some(failure) => failed.push(failure),
+            None if mode == GateMode::AnyOf => {
+                return Ok(GateResult::new(mode, gates.len(), vec![]))
+            }
+            None => {}
+        }
+    }
+
+    Ok(GateResult::new(mode, gates.len(), failed))
+}
+
+async fn check_token_requirement<L: TokenLedger>(
+    ledger: &L,
+    principal: Principal,
+    gate: &TokenGated,
+) -> CanisterResult<Option<GateFailure>> {
+    if !is_supported_token_standard(&gate.standard) {
+        return Ok(Some(GateFailure::new(
+            &gate.name,
+            format!("Unsupported token standard: {}", gate.standard),
+        )));
+    }
+
+    let balance = ledger
+        .balance_of(gate.principal, Account::from(principal))
+        .await
+        .map_err(|err| err.add_info(format!("Token gate: {}", gate.name)))?;
+
+    if balance < gate.amount {
+        return Ok(Some(GateFailure::new(
+            &gate.name,
+            format!(
+                "Balance of {balance} is below the required amount of {}",
+                gate.amount
+            ),
+        )));
+    }
+
+    Ok(None)
+}
+
+/// Accepts the common spellings like `ICRC-1`, `icrc1` and `ICRC2`
+pub fn is_supported_token_standard(standard: &str) -> bool {
+    let standard = standard.replace(['-', '_'], "");
+    SUPPORTED_TOKEN_STANDARDS
+        .iter()
+        .any(|supported| eq_str(*supported, standard.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use candid::{Nat, Principal};
+    use icrc_ledger_types::icrc1::account::Account;
+
+    use crate::{api_error::ApiError, CanisterResult};
+
+    use super::*;
+
+    /// Stand-in ledger with a fixed balance per ledger canister, the other ledgers fail
+    struct StandInLedger(Vec<(Principal, u64)>);
+
+    impl TokenLedger for StandInLedger {
+        async fn balance_of(&self, ledger: Principal, _account: Account) -> CanisterResult<Nat> {
+            self.0
+                .iter()
+                .find(|(principal, _)| *principal == ledger)
+                .map(|(_, balance)| Nat::from(*balance))
+                .ok_or_else(ledger_unavailable)
+        }
+    }
+
+    // `ApiError::new` reads the canister time, which is not available outside a canister
+    fn ledger_unavailable() -> ApiError {
+        serde_json::from_str(
+            r#"{"tag":null,"message":"Ledger unavailable","method_name":null,"error_type":"Unexpected","info":null,"timestamp":0}"#,
+        )
+        .unwrap()
+    }
+
+    // the stand-in never suspends, so a single poll completes the future
+    fn block_on<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("The stand-in ledger should not suspend"),
+        }
+    }
+
+    fn gate(name: &str, ledger: Principal, amount: u64) -> TokenGated {
+        TokenGated {
+            name: name.to_string(),
+            description: String::new(),
+            standard: "ICRC-1".to_string(),
+            principal: ledger,
+            amount,
+        }
+    }
+
+    #[test]
+    fn any_of_records_ledger_errors_as_failures() {
+        let available = Principal::from_slice(&[1]);
+        let unavailable = Principal::from_slice(&[2]);
+        let ledger = StandInLedger(vec![(available, 100)]);
+        let gates = [gate("down", unavailable, 1), gate("up", available, 50)];
+
+        let result = block_on(check_token_gate_with(
+            &ledger,
+            Principal::anonymous(),
+            &gates,
+            GateMode::AnyOf,
+        ))
+        .unwrap();
+
+        assert!(result.is_passed);
+        assert!(result.failed.is_empty());
+
+        let result = block_on(check_token_gate_with(
+            &ledger,
+            Principal::anonymous(),
+            &gates[..1],
+            GateMode::AnyOf,
+        ))
+        .unwrap();
+
+        assert!(!result.is_passed);
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].name, "down");
+    }
+
+    #[test]
+    fn all_of_returns_ledger_errors() {
+        let ledger = StandInLedger(vec![]);
+        let gates = [gate("down", Principal::from_slice(&[2]), 1)];
+
+        let result = block_on(check_token_gate_with(
+            &ledger,
+            Principal::anonymous(),
+            &gates,
+            GateMode::AllOf,
+        ));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn balance_below_amount_fails() {
+        let ledger_id = Principal::from_slice(&[1]);
+        let ledger = StandInLedger(vec![(ledger_id, 10)]);
+        let gates = [gate("token", ledger_id, 50)];
+
+        let result = block_on(check_token_gate_with(
+            &ledger,
+            Principal::anonymous(),
+            &gates,
+            GateMode::AllOf,
+        ))
+        .unwrap();
+
+        assert!(!result.is_passed);
+        assert_eq!(result.failed[0].name, "token");
+    }
+}
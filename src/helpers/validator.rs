@@ -1,5 +1,4 @@
 use email_address::EmailAddress;
-use ic_cdk::api::time;
 use std::str::FromStr;
 
 use crate::{
@@ -8,7 +7,10 @@ use crate::{
     validation::{ValidateField, ValidationResponse, ValidationType},
 };
 
-use super::str::str_len;
+use super::{
+    str::str_len,
+    time_helper::{is_valid_timezone, time},
+};
 
 pub struct Validator {
     fields: Vec<ValidateField>,
@@ -105,7 +107,7 @@ impl Validator {
     }
 
     fn validate_date_range(value: &DateRange, field: &String) -> Result<(), ValidationResponse> {
-        if value.start_date() > value.end_date() {
+        if value.is_after_end_date(value.start_date()) {
             return Err(ValidationResponse {
                 field: field.to_string(),
                 message: "The start_date is after the end_date".to_string(),
@@ -117,6 +119,17 @@ impl Validator {
                 message: "The start_date can't be in the past".to_string(),
             });
         }
+        if let Some(timezone) = &value.timezone {
+            if !is_valid_timezone(timezone) {
+                return Err(ValidationResponse {
+                    field: field.to_string(),
+                    message: format!(
+                        "The timezone {} is not a fixed offset like +02:00 or an IANA id like Europe/Amsterdam",
+                        timezone
+                    ),
+                });
+            }
+        }
 
         Ok(())
     }
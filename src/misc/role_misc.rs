@@ -8,6 +8,13 @@ pub static ADMIN_ROLE: &str = "admin";
 pub static MODERATOR_ROLE: &str = "moderator";
 pub static MEMBER_ROLE: &str = "member";
 
+// a lower rank means more authority, custom roles are ranked by their index below the moderator
+pub const OWNER_ROLE_RANK: u64 = 0;
+pub const ADMIN_ROLE_RANK: u64 = 1;
+pub const MODERATOR_ROLE_RANK: u64 = 2;
+pub const CUSTOM_ROLE_RANK_OFFSET: u64 = 3;
+pub const MEMBER_ROLE_RANK: u64 = u64::MAX;
+
 pub fn read_only_permission_actions() -> PermissionActions {
     PermissionActions::new(false, true, false, false)
 }
@@ -66,6 +73,7 @@ pub fn moderator_permissions() -> Vec<Permission> {
         create_permission(&Group(Some(read_only_permission_actions()))),
         create_permission(&Invite(Some(all_access_permission_actions()))),
         create_permission(&Member(Some(all_access_permission_actions()))),
+        create_permission(&Role(None)),
         create_permission(&ChatNotification(Some(all_access_permission_actions()))),
         create_permission(&ChatMessage(Some(all_access_permission_actions()))),
         create_permission(&ChatChannel(Some(all_access_permission_actions()))),
@@ -85,6 +93,7 @@ fn all_access_permissions() -> Vec<Permission> {
         create_permission(&Group(Some(all_access_permission_actions()))),
         create_permission(&Invite(Some(all_access_permission_actions()))),
         create_permission(&Member(Some(all_access_permission_actions()))),
+        create_permission(&Role(Some(all_access_permission_actions()))),
         create_permission(&ChatNotification(Some(all_access_permission_actions()))),
         create_permission(&ChatMessage(Some(all_access_permission_actions()))),
         create_permission(&ChatChannel(Some(all_access_permission_actions()))),
@@ -104,6 +113,7 @@ pub fn read_only_permissions() -> Vec<Permission> {
         create_permission(&Group(None)),
         create_permission(&Invite(None)),
         create_permission(&Member(None)),
+        create_permission(&Role(None)),
         create_permission(&ChatNotification(None)),
         create_permission(&ChatMessage(None)),
         create_permission(&ChatChannel(None)),
@@ -182,5 +192,10 @@ pub fn create_permission(permission: &PermissionType) -> Permission {
             true,
             actions.unwrap_or(read_only_permission_actions()),
         ),
+        Role(actions) => Permission::new(
+            permission.to_string(),
+            true,
+            actions.unwrap_or(read_only_permission_actions()),
+        ),
     }
 }
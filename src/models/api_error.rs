@@ -1,9 +1,10 @@
 use std::fmt::{self, Display};
 
 use candid::CandidType;
-use ic_cdk::api::time;
 use serde::{Deserialize, Serialize};
 
+use crate::helpers::time_helper::time;
+
 use super::validation::ValidationResponse;
 
 #[derive(Clone, CandidType, Debug, Serialize, Deserialize)]
@@ -1,10 +1,9 @@
 use std::collections::HashMap;
 
 use candid::{CandidType, Deserialize, Principal};
-use ic_cdk::api::time;
 use serde::Serialize;
 
-use crate::impl_storable_for;
+use crate::{helpers::time_helper::time, impl_storable_for};
 
 use super::invite_type::InviteType;
 
@@ -1,10 +1,9 @@
 use candid::{CandidType, Principal};
-use ic_cdk::api::time;
 use serde::{Deserialize, Serialize};
 
 use candid::{Decode, Encode};
 
-use crate::{impl_storable_for, Filter, Sorter};
+use crate::{helpers::time_helper::time, impl_storable_for, Filter, Sorter};
 
 use super::{
     date_range::DateRange,
@@ -14,6 +13,8 @@ use super::{
 
 impl_storable_for!(Boost);
 
+const NANOSECONDS_PER_SECOND: u64 = 1_000_000_000;
+
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
 pub struct Boost {
     pub subject: Subject,
@@ -43,6 +44,29 @@ impl Boost {
         self.updated_at = time();
     }
 
+    /// Adds the seconds to the remaining time, an expired boost starts a new window from now
+    pub fn extend(&mut self, seconds: u64) {
+        let remaining = self.remaining_seconds(time());
+        self.update(remaining.saturating_add(seconds));
+    }
+
+    /// The boost window starts at `updated_at`, which is reset by `update` and `extend`
+    pub fn expires_at(&self) -> u64 {
+        self.updated_at
+            .saturating_add(self.seconds.saturating_mul(NANOSECONDS_PER_SECOND))
+    }
+
+    pub fn is_active(&self, now: u64) -> bool {
+        self.updated_at <= now && now < self.expires_at()
+    }
+
+    pub fn remaining_seconds(&self, now: u64) -> u64 {
+        match self.is_active(now) {
+            true => (self.expires_at() - now) / NANOSECONDS_PER_SECOND,
+            false => 0,
+        }
+    }
+
     pub fn set_notification_id(&mut self, notification_id: u64) {
         self.notification_id = Some(notification_id);
     }
@@ -54,6 +78,15 @@ impl Boost {
 
 pub type BoostedEntry = (u64, Boost);
 
+/// The longest remaining time of the active boosts on the subject, `None` when it is not boosted
+pub fn boosted_remaining_seconds(boosts: &[Boost], subject: &Subject, now: u64) -> Option<u64> {
+    boosts
+        .iter()
+        .filter(|boost| boost.subject == *subject && boost.is_active(now))
+        .map(|boost| boost.remaining_seconds(now))
+        .max()
+}
+
 #[derive(Clone, Debug, CandidType, Deserialize)]
 pub enum BoostedSort {
     CreatedAt(SortDirection),
@@ -92,6 +125,8 @@ pub enum BoostedFilter {
     Owner(Principal),
     UpdatedAt(DateRange),
     CreatedAt(DateRange),
+    Active,
+    Blockheight(u64),
 }
 
 impl Filter<u64, Boost> for BoostedFilter {
@@ -105,6 +140,8 @@ impl Filter<u64, Boost> for BoostedFilter {
             Owner(owner) => *owner == boosted.owner,
             UpdatedAt(date) => date.is_within(boosted.updated_at),
             CreatedAt(date) => date.is_within(boosted.created_at),
+            Active => boosted.is_active(time()),
+            Blockheight(blockheight) => *blockheight == boosted.blockheight,
         }
     }
 }
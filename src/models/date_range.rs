@@ -1,10 +1,19 @@
 use candid::CandidType;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+use crate::helpers::time_helper::{
+    format_iso8601, local_to_utc_at, parse_iso8601, utc_offset_at, NANOSECONDS_PER_DAY,
+    NANOSECONDS_PER_SECOND,
+};
+
+/// A range of nanosecond timestamps, an `end_date` of `0` means the range is open-ended.
+/// The optional `timezone` is the timezone the range was entered in, a fixed offset like `+02:00` or an IANA id
+/// like `Europe/Amsterdam`, the timestamps are always UTC.
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize, PartialEq, Eq)]
 pub struct DateRange {
     pub start_date: u64,
     pub end_date: u64,
+    pub timezone: Option<String>,
 }
 
 impl DateRange {
@@ -12,9 +21,25 @@ impl DateRange {
         Self {
             start_date,
             end_date,
+            timezone: None,
         }
     }
 
+    /// A range without an end
+    pub fn starting_at(start_date: u64) -> Self {
+        Self::new(start_date, 0)
+    }
+
+    /// A range from the epoch up to the end date
+    pub fn ending_at(end_date: u64) -> Self {
+        Self::new(0, end_date)
+    }
+
+    pub fn with_timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = Some(timezone.into());
+        self
+    }
+
     pub fn start_date(&self) -> u64 {
         self.start_date
     }
@@ -23,16 +48,24 @@ impl DateRange {
         self.end_date
     }
 
-    pub fn is_within(&self, date: u64) -> bool {
-        if self.end_date == 0 {
-            return date >= self.start_date;
+    pub fn is_open_ended(&self) -> bool {
+        self.end_date == 0
+    }
+
+    /// The end date, or `u64::MAX` for open-ended ranges
+    pub fn effective_end_date(&self) -> u64 {
+        match self.is_open_ended() {
+            true => u64::MAX,
+            false => self.end_date,
         }
+    }
 
-        date >= self.start_date && date <= self.end_date
+    pub fn is_within(&self, date: u64) -> bool {
+        date >= self.start_date && date <= self.effective_end_date()
     }
 
     pub fn is_outside(&self, date: u64) -> bool {
-        date < self.start_date || date > self.end_date
+        !self.is_within(date)
     }
 
     pub fn is_before_start_date(&self, date: u64) -> bool {
@@ -44,10 +77,124 @@ impl DateRange {
     }
 
     pub fn is_before_end_date(&self, date: u64) -> bool {
-        date < self.end_date
+        date < self.effective_end_date()
     }
 
     pub fn is_after_end_date(&self, date: u64) -> bool {
-        date > self.end_date
+        date > self.effective_end_date()
+    }
+
+    /// Duration in nanoseconds, `None` for open-ended ranges
+    pub fn duration(&self) -> Option<u64> {
+        match self.is_open_ended() {
+            true => None,
+            false => Some(self.end_date.saturating_sub(self.start_date)),
+        }
+    }
+
+    pub fn overlaps(&self, other: &DateRange) -> bool {
+        self.start_date <= other.effective_end_date()
+            && other.start_date <= self.effective_end_date()
+    }
+
+    pub fn contains_range(&self, other: &DateRange) -> bool {
+        self.start_date <= other.start_date
+            && other.effective_end_date() <= self.effective_end_date()
+    }
+
+    /// The overlapping part of both ranges, keeps the timezone of `self`
+    pub fn intersection(&self, other: &DateRange) -> Option<DateRange> {
+        if !self.overlaps(other) {
+            return None;
+        }
+
+        let end_date = match self.effective_end_date().min(other.effective_end_date()) {
+            u64::MAX => 0,
+            end_date => end_date,
+        };
+
+        Some(DateRange {
+            start_date: self.start_date.max(other.start_date),
+            end_date,
+            timezone: self.timezone.clone(),
+        })
+    }
+
+    /// Offset of the timezone in seconds at the start of the range, ranges without a timezone are UTC
+    pub fn utc_offset_seconds(&self) -> i64 {
+        self.utc_offset_seconds_at(self.start_date)
+    }
+
+    /// Offset of the timezone in seconds at `timestamp`, follows the daylight saving time of IANA timezones
+    pub fn utc_offset_seconds_at(&self, timestamp: u64) -> i64 {
+        self.timezone
+            .as_deref()
+            .and_then(|timezone| utc_offset_at(timezone, timestamp))
+            .unwrap_or_default()
+    }
+
+    /// The UTC timestamp of a local date and time in the timezone of the range
+    pub fn local_to_utc(&self, local: u64) -> u64 {
+        self.timezone
+            .as_deref()
+            .and_then(|timezone| local_to_utc_at(timezone, local))
+            .unwrap_or(local)
+    }
+
+    /// Splits the range at every midnight of its timezone, open-ended ranges can not be split
+    pub fn split_by_day(&self) -> Vec<DateRange> {
+        if self.is_open_ended() {
+            return vec![self.clone()];
+        }
+
+        let mut days = vec![];
+        let mut start = self.start_date;
+        while start < self.end_date {
+            let offset = self.utc_offset_seconds_at(start) as i128 * NANOSECONDS_PER_SECOND as i128;
+            let local_day = (start as i128 + offset).div_euclid(NANOSECONDS_PER_DAY as i128);
+            let next_midnight = u64::try_from((local_day + 1) * NANOSECONDS_PER_DAY as i128)
+                .map(|local| self.local_to_utc(local))
+                .unwrap_or(self.end_date);
+
+            let day_end = next_midnight.clamp(start + 1, self.end_date);
+            days.push(DateRange {
+                start_date: start,
+                end_date: day_end,
+                timezone: self.timezone.clone(),
+            });
+            start = day_end;
+        }
+
+        if days.is_empty() {
+            days.push(self.clone());
+        }
+        days
+    }
+
+    /// ISO-8601 interval like `2024-01-31T10:00:00Z/2024-01-31T12:00:00Z`, open-ended ranges end with `..`
+    pub fn to_iso8601(&self) -> String {
+        match self.is_open_ended() {
+            true => format!("{}/..", format_iso8601(self.start_date)),
+            false => format!(
+                "{}/{}",
+                format_iso8601(self.start_date),
+                format_iso8601(self.end_date)
+            ),
+        }
+    }
+
+    /// Parses an ISO-8601 interval of two dates or date-times, an end of `..` or an empty end is open-ended
+    pub fn parse_iso8601(value: &str) -> Result<Self, String> {
+        let (start, end) = value
+            .split_once('/')
+            .ok_or_else(|| format!("Invalid ISO-8601 interval: {value}"))?;
+
+        let start_date = parse_iso8601(start)?;
+        let end_date = match end.trim() {
+            "" | ".." => 0,
+            end => parse_iso8601(end)?,
+        };
+
+        Ok(Self::new(start_date, end_date))
     }
 }
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helpers::time_helper::{hours_to_nanoseconds, NANOSECONDS_PER_DAY},
+    impl_storable_for,
+};
+
+use super::{
+    date_range::DateRange,
+    notification::{Notification, NotificationCategory, NotificationType},
+    subject::Subject,
+    user_notifications::UserNotificationData,
+};
+
+impl_storable_for!(DigestQueue);
+
+/// The amount of senders that are kept on a digest, the sender count keeps track of all of them
+pub const MAX_DIGEST_SENDERS: usize = 10;
+
+#[derive(CandidType, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub enum DigestMode {
+    #[default]
+    Immediate,
+    Hourly,
+    Daily,
+}
+
+impl DigestMode {
+    /// The end of the hour or UTC day `timestamp` falls in, `None` for immediate delivery
+    pub fn due_at(&self, timestamp: u64) -> Option<u64> {
+        let period = match self {
+            DigestMode::Immediate => return None,
+            DigestMode::Hourly => hours_to_nanoseconds(1),
+            DigestMode::Daily => NANOSECONDS_PER_DAY,
+        };
+
+        Some((timestamp / period + 1).saturating_mul(period))
+    }
+}
+
+#[derive(CandidType, Clone, Serialize, Deserialize, Debug)]
+pub struct DigestNotification {
+    pub category: NotificationCategory,
+    pub variant_name: String,
+    pub subject: Subject,
+    /// The amount of coalesced notifications
+    pub count: u64,
+    /// The amount of distinct senders
+    pub sender_count: u64,
+    /// The first `MAX_DIGEST_SENDERS` senders
+    pub senders: Vec<Principal>,
+    /// The coalesced notifications, they are not written into `UserNotifications` of the recipient
+    pub notification_ids: Vec<u64>,
+    pub window: DateRange,
+}
+
+impl DigestNotification {
+    /// Summary like `12 people joined Group X`, other variants are summarized like
+    /// `3 UserJoinGroup notifications in Group X`
+    pub fn summary(&self, subject_name: &str) -> String {
+        let people = match self.sender_count {
+            1 => "1 person".to_string(),
+            sender_count => format!("{sender_count} people"),
+        };
+
+        match self.variant_name.as_str() {
+            "UserJoinGroup" | "UserJoinEvent" => format!("{people} joined {subject_name}"),
+            "UserLeaveGroup" | "UserLeaveEvent" => format!("{people} left {subject_name}"),
+            variant_name => match self.count {
+                1 => format!("1 {variant_name} notification in {subject_name}"),
+                count => format!("{count} {variant_name} notifications in {subject_name}"),
+            },
+        }
+    }
+}
+
+/// Coalesces notifications per recipient, variant and subject
+#[derive(CandidType, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DigestKey {
+    pub recipient: Principal,
+    pub category: NotificationCategory,
+    pub variant_name: String,
+    pub subject: Subject,
+}
+
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize)]
+pub struct PendingDigest {
+    pub count: u64,
+    /// All distinct senders, only the first `MAX_DIGEST_SENDERS` are put on the digest
+    pub senders: Vec<Principal>,
+    pub notification_ids: Vec<u64>,
+    pub first_at: u64,
+    pub last_at: u64,
+    pub due_at: u64,
+}
+
+/// A flushed digest, ready to be stored as notification with the user data for the recipient
+#[derive(CandidType, Clone, Debug, Serialize, Deserialize)]
+pub struct Digest {
+    pub recipient: Principal,
+    pub notification: Notification,
+    pub user_data: UserNotificationData,
+}
+
+/// Digests that are still collecting notifications, meant to be kept in stable memory next to the notifications
+#[derive(CandidType, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DigestQueue {
+    pending: BTreeMap<DigestKey, PendingDigest>,
+}
+
+impl DigestQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Queues the notification for the recipient and returns `true` when it is coalesced into a digest.
+    /// When `false` is returned the notification has to be delivered right away, that is the case for the
+    /// immediate mode and for notifications that are not digestible.
+    pub fn add(
+        &mut self,
+        recipient: Principal,
+        notification_id: u64,
+        notification: &Notification,
+        mode: DigestMode,
+    ) -> bool {
+        let notification_type = &notification.notification_type;
+        if notification.is_actionable || !notification_type.is_digestible() {
+            return false;
+        }
+
+        let (Some(due_at), Some(subject)) = (
+            mode.due_at(notification.created_at),
+            notification_type.subjects().into_iter().next(),
+        ) else {
+            return false;
+        };
+
+        let key = DigestKey {
+            recipient,
+            category: notification_type.category(),
+            variant_name: notification_type.variant_name().to_string(),
+            subject,
+        };
+
+        let pending = self.pending.entry(key).or_insert_with(|| PendingDigest {
+            count: 0,
+            senders: vec![],
+            notification_ids: vec![],
+            first_at: notification.created_at,
+            last_at: notification.created_at,
+            due_at,
+        });
+
+        pending.count += 1;
+        pending.notification_ids.push(notification_id);
+        pending.last_at = pending.last_at.max(notification.created_at);
+        if !pending.senders.contains(&notification.sender) {
+            pending.senders.push(notification.sender);
+        }
+        true
+    }
+
+    /// Removes the digests that are due at `now`, meant to run from an interval timer
+    pub fn take_due(&mut self, now: u64) -> Vec<Digest> {
+        let due_keys: Vec<DigestKey> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| pending.due_at <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        due_keys
+            .into_iter()
+            .filter_map(|key| {
+                let pending = self.pending.remove(&key)?;
+                Some(Self::to_digest(key, pending))
+            })
+            .collect()
+    }
+
+    /// Removes all digests of the recipient, for example when they switch to immediate delivery
+    pub fn take_all_of(&mut self, recipient: Principal) -> Vec<Digest> {
+        let keys: Vec<DigestKey> = self
+            .pending
+            .keys()
+            .filter(|key| key.recipient == recipient)
+            .cloned()
+            .collect();
+
+        keys.into_iter()
+            .filter_map(|key| {
+                let pending = self.pending.remove(&key)?;
+                Some(Self::to_digest(key, pending))
+            })
+            .collect()
+    }
+
+    fn to_digest(key: DigestKey, mut pending: PendingDigest) -> Digest {
+        let sender_count = pending.senders.len() as u64;
+        pending.senders.truncate(MAX_DIGEST_SENDERS);
+
+        let digest = DigestNotification {
+            category: key.category,
+            variant_name: key.variant_name,
+            subject: key.subject,
+            count: pending.count,
+            sender_count,
+            senders: pending.senders,
+            notification_ids: pending.notification_ids,
+            window: DateRange::new(pending.first_at, pending.last_at),
+        };
+
+        Digest {
+            recipient: key.recipient,
+            notification: Notification::new(NotificationType::Digest(digest), false),
+            user_data: UserNotificationData::new(false, false)
+                .with_category(NotificationCategory::Digest),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::notification::GroupNotificationType;
+
+    use super::*;
+
+    fn principal(id: u8) -> Principal {
+        Principal::from_slice(&[id])
+    }
+
+    fn join_notification(sender: Principal) -> Notification {
+        Notification {
+            notification_type: NotificationType::Group(GroupNotificationType::UserJoinGroup(1)),
+            is_actionable: false,
+            processed_by: None,
+            is_accepted: None,
+            metadata: None,
+            sender,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn digest(variant_name: &str, count: u64, sender_count: u64) -> DigestNotification {
+        DigestNotification {
+            category: NotificationCategory::Group,
+            variant_name: variant_name.to_string(),
+            subject: Subject::Group(1),
+            count,
+            sender_count,
+            senders: vec![],
+            notification_ids: vec![],
+            window: DateRange::default(),
+        }
+    }
+
+    #[test]
+    fn summary_counts_people_by_distinct_senders() {
+        assert_eq!(
+            digest("UserJoinGroup", 3, 1).summary("Group X"),
+            "1 person joined Group X"
+        );
+        assert_eq!(
+            digest("UserLeaveEvent", 5, 2).summary("Meetup"),
+            "2 people left Meetup"
+        );
+    }
+
+    #[test]
+    fn summary_of_other_variants_counts_notifications() {
+        assert_eq!(
+            digest("GroupReminder", 1, 1).summary("Group X"),
+            "1 GroupReminder notification in Group X"
+        );
+        assert_eq!(
+            digest("GroupReminder", 3, 1).summary("Group X"),
+            "3 GroupReminder notifications in Group X"
+        );
+    }
+
+    #[test]
+    fn add_keeps_every_distinct_sender() {
+        let mut queue = DigestQueue::new();
+        let senders: Vec<Principal> = (0..=MAX_DIGEST_SENDERS as u8).map(principal).collect();
+        for (id, sender) in senders.iter().chain(senders.iter()).enumerate() {
+            let notification = join_notification(*sender);
+            assert!(queue.add(principal(100), id as u64, &notification, DigestMode::Daily));
+        }
+
+        let pending = queue.pending.values().next().expect("pending digest");
+        assert_eq!(queue.len(), 1);
+        assert_eq!(pending.count, 2 * senders.len() as u64);
+        assert_eq!(pending.senders.len(), MAX_DIGEST_SENDERS + 1);
+    }
+}
@@ -1,8 +1,9 @@
 use candid::{CandidType, Deserialize, Principal};
-use ic_cdk::{api::time, caller};
+use ic_cdk::caller;
 use serde::Serialize;
 
 use crate::{
+    helpers::time_helper::time,
     impl_storable_for,
     models::{
         asset::Asset, date_range::DateRange, location::Location, privacy::PrivacyType,
@@ -1,11 +1,16 @@
 use candid::{CandidType, Deserialize, Principal};
-use ic_cdk::{api::time, caller};
+use ic_cdk::caller;
 use serde::Serialize;
 
 use crate::{
+    helpers::time_helper::time,
     impl_storable_for,
     models::{
-        asset::Asset, date_range::DateRange, location::Location, privacy::PrivacyType,
+        asset::Asset,
+        date_range::DateRange,
+        location::{BoundingBox, Location},
+        privacy::PrivacyType,
+        role::Role,
         sort_direction::SortDirection,
     },
     CanisterResult, Filter, Sorter,
@@ -13,13 +18,23 @@ use crate::{
 
 use super::{
     api_error::ApiError,
-    boosted::Boost,
+    boosted::{boosted_remaining_seconds, Boost},
     general_structs::{
         members::Members, metadata::Metadata, privacy::Privacy, references::References,
     },
+    geo_index::GeoIndex,
+    history_event::{EventTransferred, HistoryEvent},
     invite_type::InviteType,
-    member::{Invite, Join},
+    member::{Invite, Join, RsvpStatus},
+    member_restriction::{
+        MemberRestriction, MemberRestrictionEntry, MemberRestrictionFilter, RestrictionKind,
+    },
+    ownership_transfer::OwnershipTransfer,
+    permission::{PermissionActionType, PermissionType},
+    recurrence::Recurrence,
     relation_type::RelationType,
+    subject::Subject,
+    ticket::{PostTicketType, Ticket, TicketStatus, TicketType},
 };
 
 impl_storable_for!(EventWithAttendees);
@@ -28,6 +43,10 @@ impl_storable_for!(EventWithAttendees);
 pub struct EventWithAttendees {
     pub metadata: Metadata,
     pub dates: Vec<DateRange>,
+    pub recurrence: Option<Recurrence>,
+    pub capacity: Option<u64>,
+    pub ticket_types: Option<Vec<TicketType>>,
+    pub tickets: Option<Vec<Ticket>>,
     pub privacy: Privacy,
     pub group_id: Option<u64>,
     pub created_by: Principal,
@@ -36,13 +55,18 @@ pub struct EventWithAttendees {
     pub is_canceled: Option<String>,
     pub is_deleted: bool,
     pub attendees: Members,
+    pub pending_ownership_transfer: Option<OwnershipTransfer>,
     pub updated_on: u64,
     pub created_on: u64,
 }
 
-impl From<PostEvent> for EventWithAttendees {
-    fn from(post_event: PostEvent) -> Self {
-        Self {
+impl TryFrom<PostEvent> for EventWithAttendees {
+    type Error = ApiError;
+
+    fn try_from(post_event: PostEvent) -> Result<Self, Self::Error> {
+        validate_recurrence(&post_event.recurrence)?;
+
+        Ok(Self {
             metadata: Metadata {
                 name: post_event.name.clone(),
                 description: post_event.description.clone(),
@@ -52,6 +76,10 @@ impl From<PostEvent> for EventWithAttendees {
                 location: post_event.location.clone(),
             },
             dates: vec![post_event.date],
+            recurrence: post_event.recurrence,
+            capacity: post_event.capacity,
+            ticket_types: None,
+            tickets: None,
             privacy: Privacy {
                 privacy_type: post_event.privacy,
                 privacy_gated_type_amount: None,
@@ -63,14 +91,17 @@ impl From<PostEvent> for EventWithAttendees {
             is_canceled: None,
             is_deleted: false,
             attendees: Members::new_with_owner(caller()),
+            pending_ownership_transfer: None,
             updated_on: time(),
             created_on: time(),
-        }
+        })
     }
 }
 
 impl EventWithAttendees {
-    pub fn update(&mut self, event: UpdateEvent) -> Self {
+    pub fn update(&mut self, event: UpdateEvent) -> CanisterResult<Self> {
+        validate_recurrence(&event.recurrence)?;
+
         self.metadata.name = event.name;
         self.metadata.description = event.description;
         self.metadata.website = event.website;
@@ -79,8 +110,10 @@ impl EventWithAttendees {
         self.metadata.banner_image = event.banner_image;
         self.privacy.privacy_type = event.privacy;
         self.references.tags = event.tags;
+        self.recurrence = event.recurrence;
+        self.capacity = event.capacity;
         self.updated_on = time();
-        self.clone()
+        Ok(self.clone())
     }
 
     pub fn ensured_group_id(&self) -> CanisterResult<u64> {
@@ -108,7 +141,55 @@ impl EventWithAttendees {
             .last()
             .map(|date| date.end_date())
             .unwrap_or(time());
-        DateRange::new(start_date, end_date)
+
+        DateRange {
+            start_date,
+            end_date,
+            timezone: start_dates.first().and_then(|date| date.timezone.clone()),
+        }
+    }
+
+    /// The occurrences that overlap with the range, the first date is expanded when the event recurs
+    pub fn occurrences_between(&self, range: &DateRange) -> Vec<DateRange> {
+        match (&self.recurrence, self.first_date()) {
+            (Some(recurrence), Some(first)) => recurrence.occurrences_between(&first, range),
+            _ => {
+                let mut dates: Vec<DateRange> = self
+                    .dates
+                    .iter()
+                    .filter(|date| date.overlaps(range))
+                    .cloned()
+                    .collect();
+                dates.sort_by_key(|date| date.start_date());
+                dates
+            }
+        }
+    }
+
+    /// The first occurrence that has not ended at `now`
+    pub fn next_occurrence(&self, now: u64) -> Option<DateRange> {
+        match (&self.recurrence, self.first_date()) {
+            (Some(recurrence), Some(first)) => recurrence.next_occurrence(&first, now),
+            _ => self
+                .dates
+                .iter()
+                .filter(|date| date.end_date() >= now)
+                .min_by_key(|date| date.start_date())
+                .cloned(),
+        }
+    }
+
+    /// The next occurrence, or the total date range once the event is over
+    pub fn get_next_date_range(&self, now: u64) -> DateRange {
+        self.next_occurrence(now)
+            .unwrap_or_else(|| self.get_total_date_range())
+    }
+
+    fn first_date(&self) -> Option<DateRange> {
+        self.dates
+            .iter()
+            .min_by_key(|date| date.start_date())
+            .cloned()
     }
 
     pub fn set_owner(&mut self, owner: Principal) -> Self {
@@ -117,14 +198,148 @@ impl EventWithAttendees {
         self.clone()
     }
 
+    pub fn propose_ownership_transfer(
+        &mut self,
+        actor: Principal,
+        new_owner: Principal,
+    ) -> CanisterResult<OwnershipTransfer> {
+        if actor != self.owner {
+            return Err(ApiError::unauthorized()
+                .add_method_name("propose_ownership_transfer")
+                .add_message("Only the owner can transfer the ownership")
+                .add_tag("event"));
+        }
+
+        if new_owner == self.owner || !self.can_become_owner(new_owner) {
+            return Err(ApiError::bad_request()
+                .add_method_name("propose_ownership_transfer")
+                .add_message("The ownership can not be transferred to this principal")
+                .add_tag("event"));
+        }
+
+        let transfer = OwnershipTransfer::new(actor, new_owner);
+        self.pending_ownership_transfer = Some(transfer.clone());
+        self.updated_on = time();
+        Ok(transfer)
+    }
+
+    pub fn get_pending_ownership_transfer(&self) -> Option<OwnershipTransfer> {
+        self.pending_ownership_transfer
+            .clone()
+            .filter(|transfer| !transfer.is_expired())
+    }
+
+    pub fn accept_ownership_transfer(
+        &mut self,
+        event_id: u64,
+        caller: Principal,
+    ) -> CanisterResult<HistoryEvent> {
+        let transfer = self.guard_ownership_transfer(caller, "accept_ownership_transfer")?;
+
+        // the ownership changed, or the proposed owner left or was banned after the proposal was made
+        if transfer.from != self.owner || !self.can_become_owner(transfer.to) {
+            return Err(ApiError::bad_request()
+                .add_method_name("accept_ownership_transfer")
+                .add_message("Ownership transfer is no longer valid")
+                .add_tag("event"));
+        }
+
+        self.pending_ownership_transfer = None;
+        self.owner = transfer.to;
+        self.attendees.transfer_ownership(transfer.to);
+        self.updated_on = time();
+
+        HistoryEvent::new(
+            caller,
+            &EventTransferred::new(event_id, transfer.from, transfer.to),
+        )
+        .map_err(|err| {
+            ApiError::serialize()
+                .add_method_name("accept_ownership_transfer")
+                .add_message(err)
+                .add_tag("event")
+        })
+    }
+
+    pub fn decline_ownership_transfer(
+        &mut self,
+        caller: Principal,
+    ) -> CanisterResult<OwnershipTransfer> {
+        let transfer = self.guard_ownership_transfer(caller, "decline_ownership_transfer")?;
+        self.pending_ownership_transfer = None;
+        self.updated_on = time();
+        Ok(transfer)
+    }
+
+    pub fn cancel_ownership_transfer(
+        &mut self,
+        actor: Principal,
+    ) -> CanisterResult<OwnershipTransfer> {
+        match self.pending_ownership_transfer.take() {
+            Some(transfer) if transfer.from == actor => {
+                self.updated_on = time();
+                Ok(transfer)
+            }
+            transfer => {
+                self.pending_ownership_transfer = transfer;
+                Err(ApiError::unauthorized()
+                    .add_method_name("cancel_ownership_transfer")
+                    .add_message("No pending ownership transfer proposed by the principal")
+                    .add_tag("event"))
+            }
+        }
+    }
+
+    /// The pending transfer when the caller can respond to it, leaves the transfer untouched
+    fn guard_ownership_transfer(
+        &self,
+        caller: Principal,
+        method_name: &str,
+    ) -> CanisterResult<OwnershipTransfer> {
+        let transfer = self.pending_ownership_transfer.clone().ok_or_else(|| {
+            ApiError::not_found()
+                .add_method_name(method_name)
+                .add_message("No pending ownership transfer")
+                .add_tag("event")
+        })?;
+
+        transfer
+            .guard_response(caller)
+            .map_err(|err| err.add_method_name(method_name).add_tag("event"))?;
+
+        Ok(transfer)
+    }
+
+    /// Only members that are not banned can be proposed as or become the owner
+    fn can_become_owner(&self, principal: Principal) -> bool {
+        self.attendees.is_member(principal) && !self.is_banned_member(principal)
+    }
+
+    /// Drops the pending transfer to the principal, used when it is removed or banned
+    fn clear_ownership_transfer_to(&mut self, principal: Principal) {
+        if self
+            .pending_ownership_transfer
+            .as_ref()
+            .is_some_and(|transfer| transfer.to == principal)
+        {
+            self.pending_ownership_transfer = None;
+        }
+    }
+
     pub fn delete(&mut self) -> Self {
         self.is_deleted = true;
         self.updated_on = time();
         self.clone()
     }
 
+    /// Cancels the event, valid tickets are marked for a refund
     pub fn cancel(&mut self, reason: String) -> Self {
         self.is_canceled = Some(reason);
+        for ticket in self.tickets.iter_mut().flatten() {
+            if ticket.is_valid() {
+                ticket.status = TicketStatus::RefundPending;
+            }
+        }
         self.updated_on = time();
         self.clone()
     }
@@ -133,12 +348,100 @@ impl EventWithAttendees {
         self.attendees.members.keys().cloned().collect()
     }
 
-    pub fn remove_attendee(&mut self, member: Principal) {
+    /// Removes the attendee or waitlisted principal and returns the principals promoted from the waitlist
+    pub fn remove_attendee(&mut self, member: Principal) -> Vec<Principal> {
+        self.attendees.remove_from_waitlist(&member);
         self.attendees.members.remove(&member);
+        self.clear_ownership_transfer_to(member);
+        self.promote_waitlist()
     }
 
-    pub fn add_attendee(&mut self, member: Principal) {
-        self.attendees.members.insert(member, Join::default());
+    /// Joins the event when there are spots left, otherwise the principal is placed on the waitlist.
+    /// Paid events can only be joined with a valid ticket. An attendee that declined joins again as going.
+    pub fn add_attendee(&mut self, member: Principal) -> CanisterResult<JoinStatus> {
+        if self
+            .attendees
+            .members
+            .get(&member)
+            .is_some_and(|join| join.get_rsvp() != RsvpStatus::Declined)
+        {
+            return Ok(JoinStatus::Joined);
+        }
+
+        if !self.has_ticket_access(member) {
+            return Err(ApiError::unauthorized()
+                .add_method_name("add_attendee")
+                .add_message("A valid ticket is required to join a paid event")
+                .add_tag("event"));
+        }
+
+        if !self.has_spots_left() {
+            let position = self.attendees.add_to_waitlist(member);
+            return Ok(JoinStatus::Waitlisted(position as u64));
+        }
+
+        self.attendees.remove_from_waitlist(&member);
+        self.join_as_going(member);
+        Ok(JoinStatus::Joined)
+    }
+
+    /// Declined attendees keep their join and role, other principals join with the default role
+    fn join_as_going(&mut self, member: Principal) {
+        match self.attendees.members.get_mut(&member) {
+            Some(join) => join.set_rsvp(RsvpStatus::Going),
+            None => {
+                self.attendees.members.insert(member, Join::default());
+            }
+        }
+    }
+
+    /// Free events are open to everyone, paid events only to principals with a valid ticket
+    pub fn has_ticket_access(&self, principal: Principal) -> bool {
+        !self.is_paid()
+            || self
+                .tickets
+                .iter()
+                .flatten()
+                .any(|ticket| ticket.owner == principal && ticket.is_valid())
+    }
+
+    /// The attendees that did not decline, only they take a spot
+    pub fn attending_count(&self) -> u64 {
+        self.attendees
+            .members
+            .values()
+            .filter(|join| join.get_rsvp() != RsvpStatus::Declined)
+            .count() as u64
+    }
+
+    /// `None` when the event has no capacity
+    pub fn spots_left(&self) -> Option<u64> {
+        self.capacity
+            .map(|capacity| capacity.saturating_sub(self.attending_count()))
+    }
+
+    pub fn has_spots_left(&self) -> bool {
+        self.spots_left().is_none_or(|spots_left| spots_left > 0)
+    }
+
+    /// Moves the head of the waitlist to the attendees while there are spots left, used after an attendee
+    /// leaves or the capacity is raised. Banned principals and principals without ticket access are dropped from
+    /// the waitlist.
+    pub fn promote_waitlist(&mut self) -> Vec<Principal> {
+        let mut promoted = vec![];
+        while self.has_spots_left() {
+            let Some(principal) = self.attendees.pop_waitlist() else {
+                break;
+            };
+
+            if self.attendees.is_banned(principal) || !self.has_ticket_access(principal) {
+                continue;
+            }
+
+            self.join_as_going(principal);
+            promoted.push(principal);
+        }
+        promoted
     }
 
     pub fn set_attendee_role(&mut self, member: Principal, role: String) {
@@ -147,6 +450,39 @@ impl EventWithAttendees {
         }
     }
 
+    pub fn assign_role(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        role: String,
+    ) -> CanisterResult<Join> {
+        self.attendees
+            .assign_role(actor, target, role)
+            .map_err(|err| err.add_tag("event"))
+    }
+
+    pub fn grant_role(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        role: String,
+    ) -> CanisterResult<Join> {
+        self.attendees
+            .grant_role(actor, target, role)
+            .map_err(|err| err.add_tag("event"))
+    }
+
+    pub fn revoke_role(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        role: String,
+    ) -> CanisterResult<Join> {
+        self.attendees
+            .revoke_role(actor, target, role)
+            .map_err(|err| err.add_tag("event"))
+    }
+
     pub fn get_invites(&self) -> Vec<Principal> {
         self.attendees.invites.keys().cloned().collect()
     }
@@ -172,10 +508,19 @@ impl EventWithAttendees {
         self.attendees.invites.remove(&member);
     }
 
-    pub fn convert_invite_to_attendee(&mut self, principal: Principal) {
-        if self.attendees.invites.remove(&principal).is_some() {
-            self.attendees.members.insert(principal, Join::default());
+    /// Accepts the invite, an accepted invite is waitlisted when the event is full.
+    /// `None` when the principal is not invited, the invite is kept when joining fails.
+    pub fn convert_invite_to_attendee(
+        &mut self,
+        principal: Principal,
+    ) -> CanisterResult<Option<JoinStatus>> {
+        if !self.attendees.invites.contains_key(&principal) {
+            return Ok(None);
         }
+
+        let status = self.add_attendee(principal)?;
+        self.attendees.invites.remove(&principal);
+        Ok(Some(status))
     }
 
     pub fn set_notification_id(&mut self, notification_id: u64) {
@@ -197,11 +542,436 @@ impl EventWithAttendees {
     }
 
     pub fn is_banned_member(&self, member: Principal) -> bool {
+        self.attendees.is_banned(member)
+    }
+
+    pub fn is_muted(&self, attendee: Principal) -> bool {
+        self.attendees.is_muted(attendee)
+    }
+
+    pub fn restrict_attendee(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        kind: RestrictionKind,
+        reason: String,
+        expires_at: Option<u64>,
+    ) -> CanisterResult<MemberRestriction> {
+        let restriction = MemberRestriction::new(kind, reason, actor, expires_at);
+        let restriction = self
+            .attendees
+            .restrict(actor, target, restriction)
+            .map_err(|err| err.add_tag("event"))?;
+
+        if restriction.is_kind(&RestrictionKind::Ban) {
+            self.clear_ownership_transfer_to(target);
+        }
+        self.updated_on = time();
+        Ok(restriction)
+    }
+
+    pub fn lift_restriction(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+    ) -> CanisterResult<MemberRestriction> {
+        let restriction = self
+            .attendees
+            .lift_restriction(actor, target)
+            .map_err(|err| err.add_tag("event"))?;
+
+        self.updated_on = time();
+        Ok(restriction)
+    }
+
+    pub fn get_restrictions(
+        &self,
+        filters: Vec<MemberRestrictionFilter>,
+    ) -> Vec<MemberRestrictionEntry> {
+        self.attendees.get_restrictions(filters)
+    }
+
+    pub fn get_roles(&self) -> Vec<Role> {
+        self.attendees.get_roles()
+    }
+
+    pub fn can(
+        &self,
+        attendee: Principal,
+        permission: PermissionType,
+        action: PermissionActionType,
+    ) -> bool {
+        self.attendees.can(attendee, &permission, &action)
+    }
+
+    pub fn guard_permission(
+        &self,
+        attendee: Principal,
+        permission: PermissionType,
+        action: PermissionActionType,
+    ) -> CanisterResult<()> {
         self.attendees
-            .special_members
-            .get(&member)
-            .map(|relation| relation == &RelationType::Blocked.to_string())
-            .unwrap_or_default()
+            .guard(attendee, &permission, &action)
+            .map_err(|err| err.add_tag("event"))
+    }
+
+    /// Declining keeps the attendee with the declined RSVP but releases the spot, the returned principals are
+    /// promoted from the waitlist. Taking back a decline needs a spot left.
+    pub fn set_rsvp(
+        &mut self,
+        attendee: Principal,
+        rsvp: RsvpStatus,
+    ) -> CanisterResult<(Join, Vec<Principal>)> {
+        let has_spots_left = self.has_spots_left();
+        let join = self.attendees.members.get_mut(&attendee).ok_or_else(|| {
+            ApiError::not_found()
+                .add_method_name("set_rsvp")
+                .add_message("Attendee not found")
+                .add_tag("event")
+        })?;
+
+        if rsvp == RsvpStatus::Declined && join.has_owner_role() {
+            return Err(ApiError::bad_request()
+                .add_method_name("set_rsvp")
+                .add_message("The owner can not decline the event")
+                .add_tag("event"));
+        }
+
+        if join.get_rsvp() == RsvpStatus::Declined
+            && rsvp != RsvpStatus::Declined
+            && !has_spots_left
+        {
+            return Err(ApiError::bad_request()
+                .add_method_name("set_rsvp")
+                .add_message("Event is full")
+                .add_tag("event"));
+        }
+
+        join.set_rsvp(rsvp);
+        let join = join.clone();
+
+        let promoted = match rsvp {
+            RsvpStatus::Declined => self.promote_waitlist(),
+            _ => vec![],
+        };
+        Ok((join, promoted))
+    }
+
+    /// Checks the attendee in, the actor needs the attendee edit permission
+    pub fn check_in(&mut self, actor: Principal, attendee: Principal) -> CanisterResult<Join> {
+        self.guard_permission(
+            actor,
+            PermissionType::Attendee(None),
+            PermissionActionType::Edit,
+        )
+        .map_err(|err| err.add_method_name("check_in"))?;
+
+        let join = self.attendees.members.get_mut(&attendee).ok_or_else(|| {
+            ApiError::not_found()
+                .add_method_name("check_in")
+                .add_message("Attendee not found")
+                .add_tag("event")
+        })?;
+
+        if join.is_checked_in() {
+            return Err(ApiError::duplicate()
+                .add_method_name("check_in")
+                .add_message("Attendee is already checked in")
+                .add_tag("event"));
+        }
+
+        join.check_in();
+        Ok(join.clone())
+    }
+
+    pub fn undo_check_in(&mut self, actor: Principal, attendee: Principal) -> CanisterResult<Join> {
+        self.guard_permission(
+            actor,
+            PermissionType::Attendee(None),
+            PermissionActionType::Edit,
+        )
+        .map_err(|err| err.add_method_name("undo_check_in"))?;
+
+        let join = self.attendees.members.get_mut(&attendee).ok_or_else(|| {
+            ApiError::not_found()
+                .add_method_name("undo_check_in")
+                .add_message("Attendee not found")
+                .add_tag("event")
+        })?;
+
+        join.undo_check_in();
+        Ok(join.clone())
+    }
+
+    /// Attendees that did not check in only count as no-shows once the first occurrence has ended at `now`
+    pub fn get_attendance_stats(&self, now: u64) -> AttendanceStats {
+        let has_ended = self
+            .first_date()
+            .is_some_and(|first| first.end_date() < now);
+
+        let mut stats = AttendanceStats::default();
+        for join in self.attendees.members.values() {
+            match join.get_rsvp() {
+                RsvpStatus::Going => stats.going += 1,
+                RsvpStatus::Maybe => stats.maybe += 1,
+                RsvpStatus::Declined => stats.declined += 1,
+            }
+
+            match (join.is_checked_in(), join.get_rsvp()) {
+                (true, _) => stats.checked_in += 1,
+                (false, RsvpStatus::Going) if has_ended => stats.no_shows += 1,
+                (false, _) => {}
+            }
+        }
+
+        if stats.going > 0 {
+            stats.no_show_rate = stats.no_shows as f32 / stats.going as f32;
+        }
+        stats.waitlisted = self.attendees.waitlist_count() as u64;
+        stats
+    }
+
+    pub fn get_ticket_types(&self) -> Vec<TicketType> {
+        self.ticket_types.clone().unwrap_or_default()
+    }
+
+    pub fn get_ticket_type(&self, ticket_type_id: u64) -> Option<&TicketType> {
+        self.ticket_types
+            .as_ref()?
+            .iter()
+            .find(|ticket_type| ticket_type.id == ticket_type_id)
+    }
+
+    pub fn is_paid(&self) -> bool {
+        self.ticket_types
+            .as_ref()
+            .is_some_and(|ticket_types| !ticket_types.is_empty())
+    }
+
+    pub fn get_tickets(&self) -> Vec<Ticket> {
+        self.tickets.clone().unwrap_or_default()
+    }
+
+    pub fn get_tickets_of(&self, owner: Principal) -> Vec<Ticket> {
+        self.tickets
+            .iter()
+            .flatten()
+            .filter(|ticket| ticket.owner == owner)
+            .cloned()
+            .collect()
+    }
+
+    pub fn add_ticket_type(
+        &mut self,
+        actor: Principal,
+        ticket_type: PostTicketType,
+    ) -> CanisterResult<TicketType> {
+        self.guard_permission(
+            actor,
+            PermissionType::Event(None),
+            PermissionActionType::Edit,
+        )
+        .map_err(|err| err.add_method_name("add_ticket_type"))?;
+
+        ticket_type
+            .validate()
+            .map_err(|err| err.add_method_name("add_ticket_type"))?;
+
+        let ticket_types = self.ticket_types.get_or_insert_with(Vec::new);
+        let id = ticket_types
+            .iter()
+            .map(|ticket_type| ticket_type.id + 1)
+            .max()
+            .unwrap_or_default();
+
+        let ticket_type = TicketType::new(id, ticket_type);
+        ticket_types.push(ticket_type.clone());
+        self.updated_on = time();
+        Ok(ticket_type)
+    }
+
+    /// Only ticket types without sold tickets can be removed
+    pub fn remove_ticket_type(
+        &mut self,
+        actor: Principal,
+        ticket_type_id: u64,
+    ) -> CanisterResult<TicketType> {
+        self.guard_permission(
+            actor,
+            PermissionType::Event(None),
+            PermissionActionType::Edit,
+        )
+        .map_err(|err| err.add_method_name("remove_ticket_type"))?;
+
+        let ticket_types = self.ticket_types.get_or_insert_with(Vec::new);
+        let index = ticket_types
+            .iter()
+            .position(|ticket_type| ticket_type.id == ticket_type_id)
+            .ok_or_else(|| {
+                ApiError::not_found()
+                    .add_method_name("remove_ticket_type")
+                    .add_message("Ticket type not found")
+                    .add_tag("event")
+            })?;
+
+        if ticket_types[index].sold > 0 {
+            return Err(ApiError::bad_request()
+                .add_method_name("remove_ticket_type")
+                .add_message("Ticket type has sold tickets")
+                .add_tag("event"));
+        }
+
+        let ticket_type = ticket_types.remove(index);
+        self.updated_on = time();
+        Ok(ticket_type)
+    }
+
+    /// Checks that the ticket can be bought by the buyer, meant to be called before the payment is taken
+    pub fn guard_ticket_purchase(
+        &self,
+        ticket_type_id: u64,
+        buyer: Principal,
+    ) -> CanisterResult<TicketType> {
+        if self.is_canceled.is_some() || self.is_deleted {
+            return Err(ApiError::bad_request()
+                .add_method_name("guard_ticket_purchase")
+                .add_message("Event is canceled or deleted")
+                .add_tag("event"));
+        }
+
+        if self.is_banned_member(buyer) {
+            return Err(ApiError::unauthorized()
+                .add_method_name("guard_ticket_purchase")
+                .add_message("Principal is banned from the event")
+                .add_tag("event"));
+        }
+
+        let ticket_type = self.get_ticket_type(ticket_type_id).ok_or_else(|| {
+            ApiError::not_found()
+                .add_method_name("guard_ticket_purchase")
+                .add_message("Ticket type not found")
+                .add_tag("event")
+        })?;
+
+        if !ticket_type.is_on_sale(time()) {
+            return Err(ApiError::bad_request()
+                .add_method_name("guard_ticket_purchase")
+                .add_message("Ticket type is not on sale")
+                .add_tag("event"));
+        }
+
+        if ticket_type.is_sold_out() {
+            return Err(ApiError::bad_request()
+                .add_method_name("guard_ticket_purchase")
+                .add_message("Ticket type is sold out")
+                .add_tag("event"));
+        }
+
+        // a paid ticket always grants a spot, it never places the buyer on the waitlist
+        if !self.is_attendee(buyer) && !self.has_spots_left() {
+            return Err(ApiError::bad_request()
+                .add_method_name("guard_ticket_purchase")
+                .add_message("Event is full")
+                .add_tag("event"));
+        }
+
+        Ok(ticket_type.clone())
+    }
+
+    /// Issues a ticket for a verified payment and joins the owner as attendee
+    pub fn issue_ticket(
+        &mut self,
+        ticket_type_id: u64,
+        owner: Principal,
+        blockheight: u64,
+    ) -> CanisterResult<Ticket> {
+        let ticket_type = self
+            .guard_ticket_purchase(ticket_type_id, owner)
+            .map_err(|err| err.add_method_name("issue_ticket"))?;
+
+        if self
+            .tickets
+            .iter()
+            .flatten()
+            .any(|ticket| ticket.ledger == ticket_type.ledger && ticket.blockheight == blockheight)
+        {
+            return Err(ApiError::duplicate()
+                .add_method_name("issue_ticket")
+                .add_message("Blockheight is already used for a ticket")
+                .add_info(blockheight)
+                .add_tag("event"));
+        }
+
+        let tickets = self.tickets.get_or_insert_with(Vec::new);
+        let ticket = Ticket {
+            id: tickets.len() as u64,
+            ticket_type_id,
+            owner,
+            ledger: ticket_type.ledger,
+            price: ticket_type.price,
+            blockheight,
+            status: TicketStatus::Valid,
+            purchased_at: time(),
+            refunded_at: None,
+            refund_blockheight: None,
+        };
+        tickets.push(ticket.clone());
+
+        if let Some(ticket_type) = self
+            .ticket_types
+            .iter_mut()
+            .flatten()
+            .find(|ticket_type| ticket_type.id == ticket_type_id)
+        {
+            ticket_type.sold += 1;
+        }
+
+        // the valid ticket grants access and `guard_ticket_purchase` made sure there is a spot
+        self.add_attendee(owner)?;
+        self.updated_on = time();
+        Ok(ticket)
+    }
+
+    pub fn tickets_to_refund(&self) -> Vec<Ticket> {
+        self.tickets
+            .iter()
+            .flatten()
+            .filter(|ticket| ticket.is_refund_pending())
+            .cloned()
+            .collect()
+    }
+
+    pub fn mark_ticket_refunded(
+        &mut self,
+        ticket_id: u64,
+        refund_blockheight: u64,
+    ) -> CanisterResult<Ticket> {
+        let ticket = self
+            .tickets
+            .iter_mut()
+            .flatten()
+            .find(|ticket| ticket.id == ticket_id)
+            .ok_or_else(|| {
+                ApiError::not_found()
+                    .add_method_name("mark_ticket_refunded")
+                    .add_message("Ticket not found")
+                    .add_tag("event")
+            })?;
+
+        if !ticket.is_refund_pending() {
+            return Err(ApiError::bad_request()
+                .add_method_name("mark_ticket_refunded")
+                .add_message("Ticket has no pending refund")
+                .add_info(ticket_id)
+                .add_tag("event"));
+        }
+
+        ticket.status = TicketStatus::Refunded;
+        ticket.refunded_at = Some(time());
+        ticket.refund_blockheight = Some(refund_blockheight);
+        let ticket = ticket.clone();
+        self.updated_on = time();
+        Ok(ticket)
     }
 
     pub fn is_attendee(&self, attendee: Principal) -> bool {
@@ -215,6 +985,14 @@ impl EventWithAttendees {
     pub fn is_invite_only(&self) -> bool {
         self.privacy.privacy_type == PrivacyType::InviteOnly
     }
+
+    pub fn distance_km(&self, lat: f64, lng: f64) -> Option<f64> {
+        self.metadata
+            .location
+            .physical()
+            .filter(|physical| physical.has_coordinates())
+            .map(|physical| physical.distance_km(lat, lng))
+    }
 }
 
 impl Default for EventWithAttendees {
@@ -222,6 +1000,10 @@ impl Default for EventWithAttendees {
         Self {
             metadata: Metadata::default(),
             dates: vec![DateRange::default()],
+            recurrence: None,
+            capacity: None,
+            ticket_types: None,
+            tickets: None,
             privacy: Privacy::default(),
             group_id: None,
             created_by: Principal::anonymous(),
@@ -230,6 +1012,7 @@ impl Default for EventWithAttendees {
             is_canceled: None,
             is_deleted: false,
             attendees: Members::default(),
+            pending_ownership_transfer: None,
             updated_on: time(),
             created_on: time(),
         }
@@ -238,11 +1021,41 @@ impl Default for EventWithAttendees {
 
 pub type EventEntry = (u64, EventWithAttendees);
 
+/// Going attendees that are not checked in count as no-shows once the first occurrence has ended, declined
+/// attendees do not take a spot
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, Default)]
+pub struct AttendanceStats {
+    pub going: u64,
+    pub maybe: u64,
+    pub declined: u64,
+    pub waitlisted: u64,
+    pub checked_in: u64,
+    pub no_shows: u64,
+    /// Share of the going attendees that did not check in, between `0` and `1`
+    pub no_show_rate: f32,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum JoinStatus {
+    Joined,
+    /// 1-based position on the waitlist
+    Waitlisted(u64),
+}
+
+fn validate_recurrence(recurrence: &Option<Recurrence>) -> CanisterResult<()> {
+    match recurrence {
+        Some(recurrence) => recurrence.validate(),
+        None => Ok(()),
+    }
+}
+
 #[derive(Clone, Debug, CandidType, Deserialize)]
 pub struct PostEvent {
     pub name: String,
     pub description: String,
     pub date: DateRange,
+    pub recurrence: Option<Recurrence>,
+    pub capacity: Option<u64>,
     pub privacy: PrivacyType,
     pub website: String,
     pub location: Location,
@@ -258,6 +1071,8 @@ pub struct UpdateEvent {
     pub name: String,
     pub description: String,
     pub date: DateRange,
+    pub recurrence: Option<Recurrence>,
+    pub capacity: Option<u64>,
     pub privacy: PrivacyType,
     pub website: String,
     pub location: Location,
@@ -274,6 +1089,12 @@ pub enum EventSort {
     UpdatedOn(SortDirection),
     StartDate(SortDirection),
     EndDate(SortDirection),
+    Distance {
+        lat: f32,
+        lng: f32,
+    },
+    /// Boosted subjects first, ordered by remaining boost time, the boosts are passed by the caller
+    BoostedFirst(Vec<Boost>),
 }
 
 impl Default for EventSort {
@@ -292,26 +1113,40 @@ impl Sorter<u64, EventWithAttendees> for EventSort {
             CreatedOn(Desc) => events.sort_by(|a, b| b.1.created_on.cmp(&a.1.created_on)),
             UpdatedOn(Asc) => events.sort_by(|a, b| a.1.updated_on.cmp(&b.1.updated_on)),
             UpdatedOn(Desc) => events.sort_by(|a, b| b.1.updated_on.cmp(&a.1.updated_on)),
-            StartDate(Asc) => events.sort_by(|a, b| {
-                a.1.get_total_date_range()
-                    .start_date()
-                    .cmp(&b.1.get_total_date_range().start_date())
-            }),
-            StartDate(Desc) => events.sort_by(|a, b| {
-                b.1.get_total_date_range()
-                    .start_date()
-                    .cmp(&a.1.get_total_date_range().start_date())
-            }),
-            EndDate(Asc) => events.sort_by(|a, b| {
-                a.1.get_total_date_range()
-                    .end_date()
-                    .cmp(&b.1.get_total_date_range().end_date())
-            }),
-            EndDate(Desc) => events.sort_by(|a, b| {
-                b.1.get_total_date_range()
-                    .end_date()
-                    .cmp(&a.1.get_total_date_range().end_date())
+            // recurring events are ordered by their next occurrence
+            StartDate(Asc) => {
+                let now = time();
+                events.sort_by_cached_key(|(_, event)| event.get_next_date_range(now).start_date())
+            }
+            StartDate(Desc) => {
+                let now = time();
+                events.sort_by_cached_key(|(_, event)| {
+                    std::cmp::Reverse(event.get_next_date_range(now).start_date())
+                })
+            }
+            EndDate(Asc) => {
+                let now = time();
+                events.sort_by_cached_key(|(_, event)| event.get_next_date_range(now).end_date())
+            }
+            EndDate(Desc) => {
+                let now = time();
+                events.sort_by_cached_key(|(_, event)| {
+                    std::cmp::Reverse(event.get_next_date_range(now).end_date())
+                })
+            }
+            // events without coordinates are placed at the end
+            Distance { lat, lng } => events.sort_by(|a, b| {
+                let a = a.1.distance_km(*lat as f64, *lng as f64);
+                let b = b.1.distance_km(*lat as f64, *lng as f64);
+                a.unwrap_or(f64::MAX).total_cmp(&b.unwrap_or(f64::MAX))
             }),
+            // the sort is stable, so subjects without an active boost keep their order
+            BoostedFirst(boosts) => {
+                let now = time();
+                events.sort_by_key(|(id, _)| {
+                    std::cmp::Reverse(boosted_remaining_seconds(boosts, &Subject::Event(*id), now))
+                })
+            }
         }
         events
     }
@@ -332,6 +1167,29 @@ pub enum EventFilter {
     UpdatedOn(DateRange),
     CreatedOn(DateRange),
     OptionallyInvited(Principal),
+    Near {
+        lat: f32,
+        lng: f32,
+        radius_km: f32,
+    },
+    BoundingBox(BoundingBox),
+    HasSpotsLeft,
+}
+
+impl EventFilter {
+    /// The ids the `GeoIndex` returns for the location filters, `None` for the other filters.
+    /// The candidates still have to be checked with `matches`, for example with `StorageQueryable::filter_keys`.
+    pub fn geo_candidates(&self, index: &GeoIndex) -> Option<Vec<u64>> {
+        match self {
+            EventFilter::Near {
+                lat,
+                lng,
+                radius_km,
+            } => Some(index.near(*lat as f64, *lng as f64, *radius_km as f64)),
+            EventFilter::BoundingBox(bounds) => Some(index.within(bounds)),
+            _ => None,
+        }
+    }
 }
 
 impl Filter<u64, EventWithAttendees> for EventFilter {
@@ -349,9 +1207,9 @@ impl Filter<u64, EventWithAttendees> for EventFilter {
                     return true;
                 }
 
-                date.is_within(event.get_total_date_range().start_date())
+                date.is_within(event.get_next_date_range(time()).start_date())
             }
-            EndDate(date) => date.is_within(event.get_total_date_range().end_date()),
+            EndDate(date) => date.is_within(event.get_next_date_range(time()).end_date()),
             Owner(owner) => *owner == event.owner,
             Groups(groups) => groups.contains(&event.group_id),
             Ids(ids) => ids.contains(id),
@@ -363,6 +1221,19 @@ impl Filter<u64, EventWithAttendees> for EventFilter {
                 true => event.is_attendee(*principal),
                 false => true,
             },
+            Near {
+                lat,
+                lng,
+                radius_km,
+            } => event
+                .distance_km(*lat as f64, *lng as f64)
+                .is_some_and(|distance| distance <= *radius_km as f64),
+            BoundingBox(bounds) => event
+                .metadata
+                .location
+                .physical()
+                .is_some_and(|physical| physical.is_within_bounds(bounds)),
+            HasSpotsLeft => event.has_spots_left(),
         }
     }
 }
@@ -401,10 +1272,15 @@ pub struct EventResponse {
     pub attendee_count: u64,
     pub invite_count: u64,
     pub boosted: Option<Boost>,
+    pub recurrence: Option<Recurrence>,
+    pub capacity: Option<u64>,
+    pub waitlist_count: u64,
+    pub ticket_types: Vec<TicketType>,
 }
 
 impl EventResponse {
     pub fn new(id: u64, event: EventWithAttendees, boosted: Option<Boost>) -> Self {
+        let attendee_count = event.attending_count();
         Self {
             id,
             name: event.metadata.name.clone(),
@@ -427,9 +1303,14 @@ impl EventResponse {
             updated_on: event.updated_on,
             created_on: event.created_on,
             group_id: event.group_id,
-            attendee_count: event.attendees.members.len() as u64,
+            attendee_count,
             invite_count: event.attendees.invites.len() as u64,
-            boosted,
+            // expired boosts are not returned so clients don't show them as boosted
+            boosted: boosted.filter(|boost| boost.is_active(time())),
+            recurrence: event.recurrence,
+            capacity: event.capacity,
+            waitlist_count: event.attendees.waitlist_count() as u64,
+            ticket_types: event.ticket_types.unwrap_or_default(),
         }
     }
 
@@ -447,3 +1328,115 @@ impl EventResponse {
 
 pub type EventWithAttendeesEntry = (u64, EventWithAttendees);
 pub type Attendee = (Principal, Vec<u64>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn principal(id: u8) -> Principal {
+        Principal::from_slice(&[id])
+    }
+
+    /// An event owned by `principal(1)` with `principal(2)` as attendee
+    fn event() -> EventWithAttendees {
+        let mut event = EventWithAttendees {
+            metadata: Metadata::default(),
+            dates: vec![DateRange::new(1, 2)],
+            recurrence: None,
+            capacity: None,
+            ticket_types: None,
+            tickets: None,
+            privacy: Privacy::default(),
+            group_id: Some(0),
+            created_by: principal(1),
+            owner: principal(1),
+            references: References::default(),
+            is_canceled: None,
+            is_deleted: false,
+            attendees: Members::new_with_owner(principal(1)),
+            pending_ownership_transfer: None,
+            updated_on: 0,
+            created_on: 0,
+        };
+        event
+            .add_attendee(principal(2))
+            .expect("free events can be joined");
+        event
+    }
+
+    #[test]
+    fn declining_keeps_the_attendee_and_releases_the_spot() {
+        let mut event = event();
+        event.capacity = Some(2);
+        let status = event.add_attendee(principal(3)).expect("free event");
+        assert_eq!(status, JoinStatus::Waitlisted(1));
+
+        let (join, promoted) = event
+            .set_rsvp(principal(2), RsvpStatus::Declined)
+            .expect("attendee can decline");
+
+        assert_eq!(join.get_rsvp(), RsvpStatus::Declined);
+        assert_eq!(promoted, vec![principal(3)]);
+        assert!(event.attendees.is_member(principal(2)));
+        assert_eq!(event.attending_count(), 2);
+
+        let stats = event.get_attendance_stats(0);
+        assert_eq!(stats.declined, 1);
+        assert_eq!(stats.going, 2);
+
+        // the event is full again, so the decline can not be taken back
+        assert!(event.set_rsvp(principal(2), RsvpStatus::Going).is_err());
+    }
+
+    #[test]
+    fn banning_the_proposed_owner_drops_the_transfer() {
+        let mut event = event();
+        event
+            .propose_ownership_transfer(principal(1), principal(2))
+            .expect("attendee can be proposed");
+
+        event
+            .restrict_attendee(
+                principal(1),
+                principal(2),
+                RestrictionKind::Ban,
+                String::default(),
+                None,
+            )
+            .expect("owner can ban");
+
+        assert!(event.pending_ownership_transfer.is_none());
+        assert!(event.accept_ownership_transfer(0, principal(2)).is_err());
+        assert_eq!(event.owner, principal(1));
+    }
+
+    #[test]
+    fn transfer_to_a_former_attendee_is_kept_but_can_not_be_accepted() {
+        let mut event = event();
+        event
+            .propose_ownership_transfer(principal(1), principal(2))
+            .expect("attendee can be proposed");
+
+        // left without going through `remove_attendee`
+        event.attendees.members.remove(&principal(2));
+
+        assert!(event.accept_ownership_transfer(0, principal(2)).is_err());
+        assert!(event.pending_ownership_transfer.is_some());
+        assert_eq!(event.owner, principal(1));
+    }
+
+    #[test]
+    fn declining_a_transfer_of_someone_else_keeps_it() {
+        let mut event = event();
+        event
+            .propose_ownership_transfer(principal(1), principal(2))
+            .expect("attendee can be proposed");
+
+        assert!(event.decline_ownership_transfer(principal(3)).is_err());
+        assert!(event.pending_ownership_transfer.is_some());
+
+        assert!(event.accept_ownership_transfer(0, principal(2)).is_ok());
+        assert_eq!(event.owner, principal(2));
+        assert!(event.pending_ownership_transfer.is_none());
+    }
+}
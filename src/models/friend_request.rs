@@ -1,8 +1,7 @@
 use candid::{CandidType, Decode, Encode, Principal};
-use ic_cdk::api::time;
 use serde::{Deserialize, Serialize};
 
-use crate::{impl_storable_for, Filter, Sorter};
+use crate::{helpers::time_helper::time, impl_storable_for, Filter, Sorter};
 
 use super::sort_direction::SortDirection;
 
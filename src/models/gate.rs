@@ -0,0 +1,44 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+#[derive(CandidType, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+pub enum GateMode {
+    /// Every requirement has to be met
+    #[default]
+    AllOf,
+    /// A single met requirement is enough
+    AnyOf,
+}
+
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct GateFailure {
+    pub name: String,
+    pub reason: String,
+}
+
+impl GateFailure {
+    pub fn new(name: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug, Default)]
+pub struct GateResult {
+    pub is_passed: bool,
+    pub failed: Vec<GateFailure>,
+}
+
+impl GateResult {
+    /// Gates without requirements always pass
+    pub fn new(mode: GateMode, requirement_count: usize, failed: Vec<GateFailure>) -> Self {
+        let is_passed = match mode {
+            GateMode::AllOf => failed.is_empty(),
+            GateMode::AnyOf => requirement_count == 0 || failed.len() < requirement_count,
+        };
+
+        Self { is_passed, failed }
+    }
+}
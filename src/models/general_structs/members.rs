@@ -4,16 +4,32 @@ use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    api_error::ApiError,
+    helpers::time_helper::time,
     member::{Invite, Join},
-    role::Role,
+    member_restriction::{
+        MemberRestriction, MemberRestrictionEntry, MemberRestrictionFilter, RestrictionKind,
+    },
+    misc::role_misc::{default_roles, ADMIN_ROLE, CUSTOM_ROLE_RANK_OFFSET, OWNER_ROLE},
+    permission::{Permission, PermissionActionType, PermissionType},
+    relation_type::RelationType,
+    role::{PostRole, Role},
+    str::eq_str,
+    validation::{ValidateField, ValidationType},
+    validator::Validator,
+    CanisterResult, Filter,
 };
 
+pub const ROLE_NAME_MAX_LENGTH: usize = 32;
+
 #[derive(Clone, CandidType, Serialize, Deserialize, Debug, Default)]
 pub struct Members {
     pub members: HashMap<Principal, Join>,
     pub invites: HashMap<Principal, Invite>,
     pub special_members: HashMap<Principal, String>,
     pub roles: Vec<Role>,
+    pub restrictions: Option<HashMap<Principal, MemberRestriction>>,
+    pub waitlist: Option<Vec<Principal>>,
 }
 
 impl Members {
@@ -25,6 +41,8 @@ impl Members {
             invites: Default::default(),
             special_members: Default::default(),
             roles: Default::default(),
+            restrictions: Default::default(),
+            waitlist: Default::default(),
         }
     }
 
@@ -36,9 +54,21 @@ impl Members {
         {
             join.set_member_role();
         }
-        if let Some(new_owner) = self.members.get_mut(&new_owner) {
-            new_owner.set_owner_role();
+        self.members.entry(new_owner).or_default().set_owner_role();
+    }
+
+    /// Hands the owner role to the new owner, the previous owner is demoted to admin
+    pub fn transfer_ownership(&mut self, new_owner: Principal) {
+        for (_, join) in self
+            .members
+            .iter_mut()
+            .filter(|(_, join)| join.has_owner_role())
+        {
+            join.remove_role(OWNER_ROLE.into());
+            join.remove_role(ADMIN_ROLE.into());
+            join.grant_role(ADMIN_ROLE.into());
         }
+        self.members.entry(new_owner).or_default().set_owner_role();
     }
 
     pub fn is_member(&self, member: Principal) -> bool {
@@ -48,4 +78,818 @@ impl Members {
     pub fn is_invited(&self, member: Principal) -> bool {
         self.invites.contains_key(&member)
     }
+
+    /// Members with the owner or admin role
+    pub fn get_owner_and_admins(&self) -> Vec<Principal> {
+        self.members
+            .iter()
+            .filter(|(_, join)| join.has_owner_role() || join.has_admin_role())
+            .map(|(principal, _)| *principal)
+            .collect()
+    }
+
+    /// The waitlist in FIFO order
+    pub fn get_waitlist(&self) -> Vec<Principal> {
+        self.waitlist.clone().unwrap_or_default()
+    }
+
+    pub fn waitlist_count(&self) -> usize {
+        self.waitlist.as_ref().map_or(0, |waitlist| waitlist.len())
+    }
+
+    /// 1-based position on the waitlist
+    pub fn get_waitlist_position(&self, principal: &Principal) -> Option<usize> {
+        self.waitlist
+            .as_ref()?
+            .iter()
+            .position(|waitlisted| waitlisted == principal)
+            .map(|index| index + 1)
+    }
+
+    /// Appends the principal to the waitlist and returns its position, a waitlisted principal keeps its position
+    pub fn add_to_waitlist(&mut self, principal: Principal) -> usize {
+        if let Some(position) = self.get_waitlist_position(&principal) {
+            return position;
+        }
+
+        let waitlist = self.waitlist.get_or_insert_with(Vec::new);
+        waitlist.push(principal);
+        waitlist.len()
+    }
+
+    pub fn remove_from_waitlist(&mut self, principal: &Principal) -> bool {
+        let Some(waitlist) = self.waitlist.as_mut() else {
+            return false;
+        };
+
+        let len = waitlist.len();
+        waitlist.retain(|waitlisted| waitlisted != principal);
+        waitlist.len() != len
+    }
+
+    pub fn pop_waitlist(&mut self) -> Option<Principal> {
+        let waitlist = self.waitlist.as_mut()?;
+        match waitlist.is_empty() {
+            true => None,
+            false => Some(waitlist.remove(0)),
+        }
+    }
+
+    pub fn is_banned(&self, member: Principal) -> bool {
+        self.get_restriction(member)
+            .is_some_and(|restriction| restriction.is_kind(&RestrictionKind::Ban))
+    }
+
+    pub fn is_muted(&self, member: Principal) -> bool {
+        // a timeout also takes away the right to chat
+        self.get_restriction(member).is_some_and(|restriction| {
+            restriction.is_kind(&RestrictionKind::Mute)
+                || restriction.is_kind(&RestrictionKind::Timeout)
+        })
+    }
+
+    pub fn is_timed_out(&self, member: Principal) -> bool {
+        self.get_restriction(member)
+            .is_some_and(|restriction| restriction.is_kind(&RestrictionKind::Timeout))
+    }
+
+    /// The active restriction of the member, falls back on the untyped `special_members`
+    pub fn get_restriction(&self, member: Principal) -> Option<MemberRestriction> {
+        let restriction = self
+            .restrictions
+            .as_ref()
+            .and_then(|restrictions| restrictions.get(&member))
+            .filter(|restriction| restriction.is_active());
+
+        if let Some(restriction) = restriction {
+            return Some(restriction.clone());
+        }
+
+        self.special_members
+            .get(&member)
+            .filter(|relation| **relation == RelationType::Blocked.to_string())
+            .map(|_| MemberRestriction::legacy_ban())
+    }
+
+    pub fn get_restrictions(
+        &self,
+        filters: Vec<MemberRestrictionFilter>,
+    ) -> Vec<MemberRestrictionEntry> {
+        let mut restrictions: Vec<MemberRestrictionEntry> = self
+            .special_members
+            .iter()
+            .filter(|(_, relation)| **relation == RelationType::Blocked.to_string())
+            .map(|(principal, _)| (*principal, MemberRestriction::legacy_ban()))
+            .collect();
+
+        if let Some(typed) = &self.restrictions {
+            restrictions.retain(|(principal, _)| !typed.contains_key(principal));
+            restrictions.extend(
+                typed
+                    .iter()
+                    .map(|(principal, restriction)| (*principal, restriction.clone())),
+            );
+        }
+
+        restrictions.retain(|(principal, restriction)| {
+            filters
+                .iter()
+                .all(|filter| filter.matches(principal, restriction))
+        });
+        restrictions.sort_by_key(|(_, restriction)| std::cmp::Reverse(restriction.created_at));
+        restrictions
+    }
+
+    pub fn restrict(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        restriction: MemberRestriction,
+    ) -> CanisterResult<MemberRestriction> {
+        self.guard_restriction(actor, target, &restriction.kind)?;
+
+        if restriction.kind == RestrictionKind::Timeout && restriction.expires_at.is_none() {
+            return Err(ApiError::bad_request()
+                .add_method_name("restrict")
+                .add_message("A timeout requires an expiry"));
+        }
+
+        if restriction.is_expired() {
+            return Err(ApiError::bad_request()
+                .add_method_name("restrict")
+                .add_message("The restriction is already expired"));
+        }
+
+        // a ban can only be replaced by another ban, `lift_restriction` is the only way to remove it
+        if restriction.kind != RestrictionKind::Ban && self.is_banned(target) {
+            return Err(ApiError::bad_request()
+                .add_method_name("restrict")
+                .add_message("The member is banned, lift the ban first")
+                .add_info(&restriction.kind));
+        }
+
+        if restriction.kind == RestrictionKind::Ban {
+            self.members.remove(&target);
+            self.invites.remove(&target);
+            self.remove_from_waitlist(&target);
+        }
+
+        // the typed restriction replaces the legacy ban
+        if self
+            .special_members
+            .get(&target)
+            .is_some_and(|relation| *relation == RelationType::Blocked.to_string())
+        {
+            self.special_members.remove(&target);
+        }
+
+        self.restrictions
+            .get_or_insert_with(HashMap::new)
+            .insert(target, restriction.clone());
+        Ok(restriction)
+    }
+
+    pub fn lift_restriction(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+    ) -> CanisterResult<MemberRestriction> {
+        let restriction = self.get_restriction(target).ok_or_else(|| {
+            ApiError::not_found()
+                .add_method_name("lift_restriction")
+                .add_message("Member has no active restriction")
+        })?;
+
+        self.guard_restriction(actor, target, &restriction.kind)?;
+
+        if let Some(restrictions) = self.restrictions.as_mut() {
+            restrictions.remove(&target);
+        }
+        self.special_members.remove(&target);
+        Ok(restriction)
+    }
+
+    fn guard_restriction(
+        &self,
+        actor: Principal,
+        target: Principal,
+        kind: &RestrictionKind,
+    ) -> CanisterResult<()> {
+        let action = match kind {
+            RestrictionKind::Ban => PermissionActionType::Delete,
+            RestrictionKind::Mute | RestrictionKind::Timeout => PermissionActionType::Edit,
+        };
+        self.guard(actor, &PermissionType::Member(None), &action)?;
+
+        let actor_rank = self.get_member_rank(actor).unwrap_or(u64::MAX);
+        if actor == target
+            || self
+                .get_member_rank(target)
+                .is_some_and(|target_rank| target_rank <= actor_rank)
+        {
+            return Err(ApiError::unauthorized()
+                .add_method_name("guard_restriction")
+                .add_message("Cannot restrict a member at or above your own rank")
+                .add_info(kind));
+        }
+
+        Ok(())
+    }
+
+    pub fn get_roles(&self) -> Vec<Role> {
+        // set the default protected roles
+        let mut roles = default_roles();
+
+        // append the custom roles stored on the group or event
+        roles.append(&mut self.roles.clone());
+        roles
+    }
+
+    pub fn get_member_roles(&self, member: Principal) -> Vec<Role> {
+        let Some(join) = self.members.get(&member) else {
+            return vec![];
+        };
+
+        self.get_roles()
+            .into_iter()
+            .filter(|role| join.roles.contains(&role.name))
+            .collect()
+    }
+
+    pub fn get_member_permissions(&self, member: Principal) -> Vec<Permission> {
+        self.get_member_roles(member)
+            .into_iter()
+            .flat_map(|role| role.permissions)
+            .collect()
+    }
+
+    pub fn can(
+        &self,
+        member: Principal,
+        permission: &PermissionType,
+        action: &PermissionActionType,
+    ) -> bool {
+        if self.is_banned(member) {
+            return false;
+        }
+
+        let is_read = *action == PermissionActionType::Read;
+        if !is_read
+            && (self.is_timed_out(member) || (self.is_muted(member) && permission.is_chat()))
+        {
+            return false;
+        }
+
+        // the effective permissions are the union of all roles the member holds
+        self.get_member_permissions(member)
+            .iter()
+            .any(|p| p.allows(permission, action))
+    }
+
+    pub fn guard(
+        &self,
+        member: Principal,
+        permission: &PermissionType,
+        action: &PermissionActionType,
+    ) -> CanisterResult<()> {
+        if self.can(member, permission, action) {
+            return Ok(());
+        }
+
+        let mut err = ApiError::unauthorized()
+            .add_method_name("guard")
+            .add_message(format!("Missing permission: {permission}.{action}"))
+            .add_info(format!("{permission}.{action}"));
+
+        if let Some(restriction) = self.get_restriction(member) {
+            err = err.add_info(restriction.kind);
+        } else if !self.is_member(member) {
+            err = err.add_info("not a member");
+        }
+
+        Err(err)
+    }
+
+    pub fn get_role(&self, name: &str) -> Option<Role> {
+        self.get_roles().into_iter().find(|role| role.name == name)
+    }
+
+    /// The rank of the most authoritative role the member holds
+    pub fn get_member_rank(&self, member: Principal) -> Option<u64> {
+        self.get_member_roles(member)
+            .iter()
+            .map(|role| role.rank())
+            .min()
+    }
+
+    /// Checks if the actor is allowed to grant or revoke the role on the target,
+    /// both the role and every role the target holds have to be ranked below the actor
+    pub fn guard_role_change(
+        &self,
+        actor: Principal,
+        target: Principal,
+        role: &str,
+    ) -> CanisterResult<Role> {
+        self.guard(
+            actor,
+            &PermissionType::Member(None),
+            &PermissionActionType::Edit,
+        )?;
+
+        let err = |err: ApiError, message: &str| {
+            err.add_method_name("guard_role_change")
+                .add_message(message)
+                .add_info(role)
+        };
+
+        if !self.is_member(target) {
+            return Err(err(ApiError::not_found(), "Target is not a member"));
+        }
+
+        let role = self
+            .get_role(role)
+            .ok_or_else(|| err(ApiError::not_found(), "Role does not exist"))?;
+
+        if role.name == OWNER_ROLE {
+            return Err(err(
+                ApiError::bad_request(),
+                "The owner role can only be changed by transferring the ownership",
+            ));
+        }
+
+        let actor_rank = self
+            .get_member_rank(actor)
+            .ok_or_else(|| err(ApiError::unauthorized(), "Actor does not hold any role"))?;
+
+        if actor == target {
+            return Err(err(
+                ApiError::unauthorized(),
+                "Cannot change your own roles",
+            ));
+        }
+
+        if role.rank() <= actor_rank {
+            return Err(err(
+                ApiError::unauthorized(),
+                "Cannot grant or revoke a role at or above your own rank",
+            ));
+        }
+
+        if self
+            .get_member_rank(target)
+            .is_some_and(|target_rank| target_rank <= actor_rank)
+        {
+            return Err(err(
+                ApiError::unauthorized(),
+                "Cannot change the roles of a member at or above your own rank",
+            ));
+        }
+
+        Ok(role)
+    }
+
+    pub fn assign_role(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        role: String,
+    ) -> CanisterResult<Join> {
+        let role = self.guard_role_change(actor, target, &role)?;
+
+        let join = self
+            .members
+            .get_mut(&target)
+            .ok_or_else(|| ApiError::not_found().add_method_name("assign_role"))?;
+
+        join.roles = vec![role.name];
+        join.updated_at = time();
+        Ok(join.clone())
+    }
+
+    pub fn grant_role(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        role: String,
+    ) -> CanisterResult<Join> {
+        let role = self.guard_role_change(actor, target, &role)?;
+
+        let join = self
+            .members
+            .get_mut(&target)
+            .ok_or_else(|| ApiError::not_found().add_method_name("grant_role"))?;
+
+        if join.has_role(&role.name) {
+            return Err(ApiError::duplicate()
+                .add_method_name("grant_role")
+                .add_message("Member already holds the role")
+                .add_info(role.name));
+        }
+
+        join.grant_role(role.name);
+        Ok(join.clone())
+    }
+
+    pub fn revoke_role(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        role: String,
+    ) -> CanisterResult<Join> {
+        let role = self.guard_role_change(actor, target, &role)?;
+
+        let join = self
+            .members
+            .get_mut(&target)
+            .ok_or_else(|| ApiError::not_found().add_method_name("revoke_role"))?;
+
+        if !join.has_role(&role.name) {
+            return Err(ApiError::not_found()
+                .add_method_name("revoke_role")
+                .add_message("Member does not hold the role")
+                .add_info(role.name));
+        }
+
+        join.revoke_role(role.name);
+        Ok(join.clone())
+    }
+
+    pub fn add_role(&mut self, actor: Principal, post_role: PostRole) -> CanisterResult<Role> {
+        let actor_rank = self.guard_role_management(actor, "add_role")?;
+
+        let mut role = Role::from(post_role);
+        role.name = role.name.trim().to_string();
+        self.validate_role_name(&role.name, None)?;
+
+        let position = Self::role_position(role.index, self.roles.len());
+        Self::guard_role_rank(actor_rank, position_rank(position), &role.name, "add_role")?;
+
+        Ok(self.place_role(role))
+    }
+
+    pub fn update_role(
+        &mut self,
+        actor: Principal,
+        name: &str,
+        post_role: PostRole,
+    ) -> CanisterResult<Role> {
+        let actor_rank = self.guard_role_management(actor, "update_role")?;
+        let position = self.get_custom_role_position(name, "update_role")?;
+        Self::guard_role_rank(actor_rank, self.roles[position].rank(), name, "update_role")?;
+
+        let mut role = Role::from(post_role);
+        role.name = role.name.trim().to_string();
+        self.validate_role_name(&role.name, Some(name))?;
+
+        // the role is placed among the other custom roles, so one less than the current count
+        let new_position = Self::role_position(
+            role.index.or(self.roles[position].index),
+            self.roles.len() - 1,
+        );
+        Self::guard_role_rank(
+            actor_rank,
+            position_rank(new_position),
+            &role.name,
+            "update_role",
+        )?;
+
+        let old_role = self.roles.remove(position);
+        role.index = role.index.or(old_role.index);
+        let role = self.place_role(role);
+
+        if old_role.name != role.name {
+            for join in self.members.values_mut() {
+                for held in join.roles.iter_mut().filter(|held| **held == old_role.name) {
+                    *held = role.name.clone();
+                }
+            }
+        }
+
+        Ok(role)
+    }
+
+    /// Removes the custom role and strips it from every member that holds it,
+    /// returns the members that were affected
+    pub fn remove_role(&mut self, actor: Principal, name: &str) -> CanisterResult<Vec<Principal>> {
+        let actor_rank = self.guard_role_management(actor, "remove_role")?;
+        let position = self.get_custom_role_position(name, "remove_role")?;
+        Self::guard_role_rank(actor_rank, self.roles[position].rank(), name, "remove_role")?;
+        let role = self.roles.remove(position);
+        self.normalize_role_indices();
+
+        let mut affected = vec![];
+        for (principal, join) in self.members.iter_mut() {
+            if !join.roles.contains(&role.name) {
+                continue;
+            }
+
+            join.revoke_role(role.name.clone());
+            affected.push(*principal);
+        }
+
+        Ok(affected)
+    }
+
+    /// Reorders the custom roles, the `names` should contain every custom role exactly once
+    pub fn reorder_roles(
+        &mut self,
+        actor: Principal,
+        names: Vec<String>,
+    ) -> CanisterResult<Vec<Role>> {
+        let actor_rank = self.guard_role_management(actor, "reorder_roles")?;
+
+        let is_complete = names.len() == self.roles.len()
+            && self
+                .roles
+                .iter()
+                .all(|role| names.iter().filter(|name| **name == role.name).count() == 1);
+
+        if !is_complete {
+            return Err(ApiError::bad_request()
+                .add_method_name("reorder_roles")
+                .add_message("The new order should contain every custom role exactly once"));
+        }
+
+        if let Some(role) = self.roles.iter().find(|role| role.protected) {
+            return Err(ApiError::unauthorized()
+                .add_method_name("reorder_roles")
+                .add_message("Protected roles cannot be modified")
+                .add_info(&role.name));
+        }
+
+        // a role that moves has to stay below the actor, both at its current and at its new position
+        for role in &self.roles {
+            let new_rank = names
+                .iter()
+                .position(|name| *name == role.name)
+                .map(position_rank)
+                .unwrap_or(role.rank());
+
+            if new_rank != role.rank() {
+                Self::guard_role_rank(actor_rank, role.rank(), &role.name, "reorder_roles")?;
+                Self::guard_role_rank(actor_rank, new_rank, &role.name, "reorder_roles")?;
+            }
+        }
+
+        for role in self.roles.iter_mut() {
+            role.index = names
+                .iter()
+                .position(|name| *name == role.name)
+                .map(|index| index as u64);
+        }
+
+        self.normalize_role_indices();
+        Ok(self.roles.clone())
+    }
+
+    /// Checks the role edit permission and returns the rank of the actor
+    fn guard_role_management(&self, actor: Principal, method_name: &str) -> CanisterResult<u64> {
+        self.guard(
+            actor,
+            &PermissionType::Role(None),
+            &PermissionActionType::Edit,
+        )?;
+
+        self.get_member_rank(actor).ok_or_else(|| {
+            ApiError::unauthorized()
+                .add_method_name(method_name)
+                .add_message("Actor does not hold any role")
+        })
+    }
+
+    /// Roles can only be managed below the own rank, so a role can not be raised to or above its holder
+    fn guard_role_rank(
+        actor_rank: u64,
+        rank: u64,
+        name: &str,
+        method_name: &str,
+    ) -> CanisterResult<()> {
+        if rank <= actor_rank {
+            return Err(ApiError::unauthorized()
+                .add_method_name(method_name)
+                .add_message("Cannot manage a role at or above your own rank")
+                .add_info(name));
+        }
+        Ok(())
+    }
+
+    /// The position `place_role` inserts a role with the `index` at among `count` other custom roles
+    fn role_position(index: Option<u64>, count: usize) -> usize {
+        index
+            .map(|index| (index as usize).min(count))
+            .unwrap_or(count)
+    }
+
+    /// Inserts the role at its requested `index`, or after the other custom roles when it has none,
+    /// and renumbers the custom roles so every index is unique. Returns the stored role.
+    fn place_role(&mut self, role: Role) -> Role {
+        self.normalize_role_indices();
+
+        let position = Self::role_position(role.index, self.roles.len());
+
+        self.roles.insert(position, role);
+        self.normalize_role_indices();
+        self.roles[position].clone()
+    }
+
+    /// Sorts the custom roles by index, roles without an index go last, and numbers them from 0
+    fn normalize_role_indices(&mut self) {
+        self.roles
+            .sort_by_key(|role| role.index.unwrap_or(u64::MAX));
+        for (index, role) in self.roles.iter_mut().enumerate() {
+            role.index = Some(index as u64);
+        }
+    }
+
+    fn get_custom_role_position(&self, name: &str, method_name: &str) -> CanisterResult<usize> {
+        if default_roles().iter().any(|role| role.name == name) {
+            return Err(ApiError::unauthorized()
+                .add_method_name(method_name)
+                .add_message("Default roles cannot be modified")
+                .add_info(name));
+        }
+
+        let position = self
+            .roles
+            .iter()
+            .position(|role| role.name == name)
+            .ok_or_else(|| {
+                ApiError::not_found()
+                    .add_method_name(method_name)
+                    .add_message("Role does not exist")
+                    .add_info(name)
+            })?;
+
+        if self.roles[position].protected {
+            return Err(ApiError::unauthorized()
+                .add_method_name(method_name)
+                .add_message("Protected roles cannot be modified")
+                .add_info(name));
+        }
+
+        Ok(position)
+    }
+
+    fn validate_role_name(&self, name: &str, current_name: Option<&str>) -> CanisterResult<()> {
+        Validator::new(vec![ValidateField(
+            ValidationType::StringLength(name.trim().to_string(), 1, ROLE_NAME_MAX_LENGTH),
+            "name".to_string(),
+        )])
+        .validate()?;
+
+        if default_roles()
+            .iter()
+            .any(|role| eq_str(&role.name, &name.to_string()))
+        {
+            return Err(ApiError::duplicate()
+                .add_method_name("validate_role_name")
+                .add_message("Role name is reserved for a default role")
+                .add_info(name));
+        }
+
+        let is_taken = self
+            .roles
+            .iter()
+            .filter(|role| Some(role.name.as_str()) != current_name)
+            .any(|role| eq_str(&role.name, &name.to_string()));
+
+        if is_taken {
+            return Err(ApiError::duplicate()
+                .add_method_name("validate_role_name")
+                .add_message("Role name already exists")
+                .add_info(name));
+        }
+
+        Ok(())
+    }
+}
+
+/// The rank of the custom role at the position once the indices are normalized
+fn position_rank(position: usize) -> u64 {
+    CUSTOM_ROLE_RANK_OFFSET.saturating_add(position as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::misc::role_misc::all_access_permission_actions;
+
+    fn principal(id: u8) -> Principal {
+        Principal::from_slice(&[id])
+    }
+
+    fn custom_role(name: &str, index: u64, permissions: Vec<Permission>) -> Role {
+        Role::new(
+            name.to_string(),
+            false,
+            permissions,
+            String::default(),
+            Some(index),
+        )
+    }
+
+    fn post_role(name: &str, index: Option<u64>) -> PostRole {
+        PostRole {
+            name: name.to_string(),
+            permissions: vec![],
+            color: String::default(),
+            index,
+        }
+    }
+
+    /// `manager` can edit roles and is ranked above `helper` and `guest`
+    fn members() -> Members {
+        let role_edit = Permission::new(
+            PermissionType::Role(None).to_string(),
+            false,
+            all_access_permission_actions(),
+        );
+
+        let mut members = Members {
+            roles: vec![
+                custom_role("manager", 0, vec![role_edit]),
+                custom_role("helper", 1, vec![]),
+                custom_role("guest", 2, vec![]),
+            ],
+            ..Default::default()
+        };
+        members.members.insert(
+            principal(1),
+            Join {
+                roles: vec!["manager".to_string()],
+                ..Join::default()
+            },
+        );
+        members
+    }
+
+    fn names(members: &Members) -> Vec<String> {
+        members.roles.iter().map(|role| role.name.clone()).collect()
+    }
+
+    #[test]
+    fn roles_below_the_actor_can_be_managed() {
+        let mut members = members();
+
+        assert!(members
+            .update_role(principal(1), "guest", post_role("visitor", None))
+            .is_ok());
+        assert!(members
+            .reorder_roles(
+                principal(1),
+                vec!["manager".into(), "visitor".into(), "helper".into()],
+            )
+            .is_ok());
+        assert!(members
+            .add_role(principal(1), post_role("intern", Some(1)))
+            .is_ok());
+        assert_eq!(
+            names(&members),
+            vec!["manager", "intern", "visitor", "helper"]
+        );
+    }
+
+    #[test]
+    fn own_role_can_not_be_managed() {
+        let mut members = members();
+
+        assert!(members
+            .update_role(principal(1), "manager", post_role("manager", None))
+            .is_err());
+        assert!(members.remove_role(principal(1), "manager").is_err());
+        assert_eq!(names(&members), vec!["manager", "helper", "guest"]);
+    }
+
+    #[test]
+    fn roles_can_not_be_placed_at_or_above_the_actor() {
+        let mut members = members();
+
+        assert!(members
+            .add_role(principal(1), post_role("boss", Some(0)))
+            .is_err());
+        assert!(members
+            .update_role(principal(1), "helper", post_role("helper", Some(0)))
+            .is_err());
+        assert!(members
+            .reorder_roles(
+                principal(1),
+                vec!["helper".into(), "manager".into(), "guest".into()],
+            )
+            .is_err());
+        assert_eq!(names(&members), vec!["manager", "helper", "guest"]);
+    }
+
+    #[test]
+    fn role_management_requires_the_role_permission() {
+        let mut members = members();
+        members.members.insert(
+            principal(2),
+            Join {
+                roles: vec!["helper".to_string()],
+                ..Join::default()
+            },
+        );
+
+        assert!(members.remove_role(principal(2), "guest").is_err());
+    }
 }
@@ -0,0 +1,132 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helpers::geo::{
+        geohash_cell_size, geohash_cells_between, geohash_precision_for_radius, EARTH_RADIUS_KM,
+    },
+    impl_storable_for,
+};
+
+use super::location::{BoundingBox, Location, PhysicalLocation};
+
+impl_storable_for!(GeoIndex);
+
+pub const GEO_INDEX_PRECISION: usize = 9;
+
+// the amount of cells a bounding box lookup is allowed to scan before falling back to a coarser precision
+const MAX_BOUNDING_BOX_CELLS: usize = 32;
+
+/// Secondary index that maps entity ids to the geohash of their physical location.
+/// Lookups return candidate ids, see `GroupFilter::geo_candidates` and `EventFilter::geo_candidates`,
+/// the exact distance check is still done by the `Near` and `BoundingBox` filters.
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct GeoIndex {
+    hashes: BTreeMap<u64, String>,
+    cells: BTreeSet<(String, u64)>,
+}
+
+impl GeoIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn contains(&self, id: &u64) -> bool {
+        self.hashes.contains_key(id)
+    }
+
+    pub fn insert(&mut self, id: u64, location: &PhysicalLocation) {
+        self.remove(id);
+
+        if !location.has_coordinates() {
+            return;
+        }
+
+        let hash = location.geohash(GEO_INDEX_PRECISION);
+        self.cells.insert((hash.clone(), id));
+        self.hashes.insert(id, hash);
+    }
+
+    /// Indexes the physical part of the location, or removes the id when there is none
+    pub fn upsert(&mut self, id: u64, location: &Location) {
+        match location.physical() {
+            Some(physical) => self.insert(id, physical),
+            None => self.remove(id),
+        }
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        if let Some(hash) = self.hashes.remove(&id) {
+            self.cells.remove(&(hash, id));
+        }
+    }
+
+    pub fn near(&self, lat: f64, lng: f64, radius_km: f64) -> Vec<u64> {
+        if radius_km <= 0.0 {
+            return vec![];
+        }
+
+        let lat_delta = (radius_km / EARTH_RADIUS_KM).to_degrees();
+        let lng_delta = match lat.abs() + lat_delta >= 90.0 {
+            true => 180.0,
+            false => lat_delta / lat.to_radians().cos(),
+        };
+
+        let precision = geohash_precision_for_radius(radius_km, lat).min(GEO_INDEX_PRECISION);
+        self.ids_in_cells(geohash_cells_between(
+            lat - lat_delta,
+            lng - lng_delta.min(180.0),
+            lat + lat_delta,
+            lng + lng_delta.min(180.0),
+            precision,
+        ))
+    }
+
+    pub fn within(&self, bounds: &BoundingBox) -> Vec<u64> {
+        let lat_span = (bounds.max_lat - bounds.min_lat).abs() as f64;
+        let lng_span = match bounds.crosses_antimeridian() {
+            true => (bounds.max_lng + 360.0 - bounds.min_lng) as f64,
+            false => (bounds.max_lng - bounds.min_lng) as f64,
+        };
+
+        let precision = (1..=GEO_INDEX_PRECISION)
+            .rev()
+            .find(|precision| {
+                let (lat_step, lng_step) = geohash_cell_size(*precision);
+                let cells = (lat_span / lat_step + 1.0) * (lng_span / lng_step + 1.0);
+                cells <= MAX_BOUNDING_BOX_CELLS as f64
+            })
+            .unwrap_or(1);
+
+        self.ids_in_cells(geohash_cells_between(
+            bounds.min_lat as f64,
+            bounds.min_lng as f64,
+            bounds.max_lat as f64,
+            bounds.max_lng as f64,
+            precision,
+        ))
+    }
+
+    fn ids_in_cells(&self, prefixes: Vec<String>) -> Vec<u64> {
+        let mut ids: BTreeSet<u64> = BTreeSet::new();
+        for prefix in prefixes {
+            ids.extend(
+                self.cells
+                    .range((prefix.clone(), 0)..)
+                    .take_while(|(hash, _)| hash.starts_with(&prefix))
+                    .map(|(_, id)| *id),
+            );
+        }
+        ids.into_iter().collect()
+    }
+}
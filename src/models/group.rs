@@ -1,10 +1,11 @@
 use std::collections::HashMap;
 
 use candid::{CandidType, Deserialize, Principal};
-use ic_cdk::{api::time, caller};
+use ic_cdk::caller;
 use serde::Serialize;
 
 use crate::{
+    helpers::time_helper::time,
     impl_storable_for,
     misc::role_misc::default_roles,
     models::{asset::Asset, location::Location, privacy::PrivacyType, role::Role},
@@ -1,29 +1,44 @@
 use std::collections::HashMap;
 
 use candid::{CandidType, Deserialize, Principal};
-use ic_cdk::{api::time, caller};
+use ic_cdk::caller;
 use serde::Serialize;
 
 use crate::{
+    helpers::time_helper::time,
     impl_storable_for,
     misc::role_misc::default_roles,
     models::{
-        asset::Asset, date_range::DateRange, location::Location, privacy::PrivacyType, role::Role,
+        asset::Asset,
+        date_range::DateRange,
+        location::{BoundingBox, Location},
+        privacy::PrivacyType,
+        role::{PostRole, Role},
         sort_direction::SortDirection,
     },
-    Filter, Sorter,
+    CanisterResult, Filter, Sorter,
 };
 
 use super::{
     api_error::ApiError,
-    boosted::Boost,
+    boosted::{boosted_remaining_seconds, Boost},
     general_structs::{
         members::Members, metadata::Metadata, privacy::Privacy, references::References,
     },
+    geo_index::GeoIndex,
+    history_event::{
+        GroupRoleChangeKind, GroupRoleChanged, GroupRoleUpdateKind, GroupRoleUpdated,
+        GroupTransferred, HistoryEvent,
+    },
     invite_type::InviteType,
     member::{Invite, Join},
-    permission::Permission,
+    member_restriction::{
+        MemberRestriction, MemberRestrictionEntry, MemberRestrictionFilter, RestrictionKind,
+    },
+    ownership_transfer::OwnershipTransfer,
+    permission::{Permission, PermissionActionType, PermissionType},
     relation_type::RelationType,
+    subject::Subject,
 };
 
 impl_storable_for!(GroupWithMembers);
@@ -40,6 +55,7 @@ pub struct GroupWithMembers {
     pub wallets: HashMap<Principal, String>,
     pub events: Vec<u64>,
     pub is_deleted: bool,
+    pub pending_ownership_transfer: Option<OwnershipTransfer>,
     pub updated_on: u64,
     pub created_on: u64,
 }
@@ -54,6 +70,7 @@ impl Default for GroupWithMembers {
             members: Default::default(),
             references: Default::default(),
             is_deleted: Default::default(),
+            pending_ownership_transfer: Default::default(),
             updated_on: Default::default(),
             created_on: Default::default(),
             matrix_space_id: Default::default(),
@@ -86,6 +103,7 @@ impl From<PostGroup> for GroupWithMembers {
                 tags: group.tags,
             },
             is_deleted: false,
+            pending_ownership_transfer: None,
             updated_on: time(),
             created_on: time(),
             matrix_space_id: group.matrix_space_id,
@@ -116,6 +134,134 @@ impl GroupWithMembers {
         self.clone()
     }
 
+    pub fn propose_ownership_transfer(
+        &mut self,
+        actor: Principal,
+        new_owner: Principal,
+    ) -> CanisterResult<OwnershipTransfer> {
+        if actor != self.owner {
+            return Err(ApiError::unauthorized()
+                .add_method_name("propose_ownership_transfer")
+                .add_message("Only the owner can transfer the ownership")
+                .add_tag("group"));
+        }
+
+        if new_owner == self.owner || !self.can_become_owner(new_owner) {
+            return Err(ApiError::bad_request()
+                .add_method_name("propose_ownership_transfer")
+                .add_message("The ownership can not be transferred to this principal")
+                .add_tag("group"));
+        }
+
+        let transfer = OwnershipTransfer::new(actor, new_owner);
+        self.pending_ownership_transfer = Some(transfer.clone());
+        self.updated_on = time();
+        Ok(transfer)
+    }
+
+    pub fn get_pending_ownership_transfer(&self) -> Option<OwnershipTransfer> {
+        self.pending_ownership_transfer
+            .clone()
+            .filter(|transfer| !transfer.is_expired())
+    }
+
+    pub fn accept_ownership_transfer(
+        &mut self,
+        group_id: u64,
+        caller: Principal,
+    ) -> CanisterResult<HistoryEvent> {
+        let transfer = self.guard_ownership_transfer(caller, "accept_ownership_transfer")?;
+
+        // the ownership changed, or the proposed owner left or was banned after the proposal was made
+        if transfer.from != self.owner || !self.can_become_owner(transfer.to) {
+            return Err(ApiError::bad_request()
+                .add_method_name("accept_ownership_transfer")
+                .add_message("Ownership transfer is no longer valid")
+                .add_tag("group"));
+        }
+
+        self.pending_ownership_transfer = None;
+        self.owner = transfer.to;
+        self.members.transfer_ownership(transfer.to);
+        self.updated_on = time();
+
+        HistoryEvent::new(
+            caller,
+            &GroupTransferred::new(group_id, transfer.from, transfer.to),
+        )
+        .map_err(|err| {
+            ApiError::serialize()
+                .add_method_name("accept_ownership_transfer")
+                .add_message(err)
+                .add_tag("group")
+        })
+    }
+
+    pub fn decline_ownership_transfer(
+        &mut self,
+        caller: Principal,
+    ) -> CanisterResult<OwnershipTransfer> {
+        let transfer = self.guard_ownership_transfer(caller, "decline_ownership_transfer")?;
+        self.pending_ownership_transfer = None;
+        self.updated_on = time();
+        Ok(transfer)
+    }
+
+    pub fn cancel_ownership_transfer(
+        &mut self,
+        actor: Principal,
+    ) -> CanisterResult<OwnershipTransfer> {
+        match self.pending_ownership_transfer.take() {
+            Some(transfer) if transfer.from == actor => {
+                self.updated_on = time();
+                Ok(transfer)
+            }
+            transfer => {
+                self.pending_ownership_transfer = transfer;
+                Err(ApiError::unauthorized()
+                    .add_method_name("cancel_ownership_transfer")
+                    .add_message("No pending ownership transfer proposed by the principal")
+                    .add_tag("group"))
+            }
+        }
+    }
+
+    /// The pending transfer when the caller can respond to it, leaves the transfer untouched
+    fn guard_ownership_transfer(
+        &self,
+        caller: Principal,
+        method_name: &str,
+    ) -> CanisterResult<OwnershipTransfer> {
+        let transfer = self.pending_ownership_transfer.clone().ok_or_else(|| {
+            ApiError::not_found()
+                .add_method_name(method_name)
+                .add_message("No pending ownership transfer")
+                .add_tag("group")
+        })?;
+
+        transfer
+            .guard_response(caller)
+            .map_err(|err| err.add_method_name(method_name).add_tag("group"))?;
+
+        Ok(transfer)
+    }
+
+    /// Only members that are not banned can be proposed as or become the owner
+    fn can_become_owner(&self, principal: Principal) -> bool {
+        self.members.is_member(principal) && !self.is_banned_member(principal)
+    }
+
+    /// Drops the pending transfer to the principal, used when it is removed or banned
+    fn clear_ownership_transfer_to(&mut self, principal: Principal) {
+        if self
+            .pending_ownership_transfer
+            .as_ref()
+            .is_some_and(|transfer| transfer.to == principal)
+        {
+            self.pending_ownership_transfer = None;
+        }
+    }
+
     pub fn delete(&mut self) -> Self {
         self.is_deleted = true;
         self.updated_on = time();
@@ -128,6 +274,7 @@ impl GroupWithMembers {
 
     pub fn remove_member(&mut self, member: Principal) {
         self.members.members.remove(&member);
+        self.clear_ownership_transfer_to(member);
     }
 
     pub fn add_member(&mut self, member: Principal) {
@@ -140,6 +287,84 @@ impl GroupWithMembers {
         }
     }
 
+    pub fn assign_role(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        role: String,
+    ) -> CanisterResult<Join> {
+        self.members
+            .assign_role(actor, target, role)
+            .map_err(|err| err.add_tag("group"))
+    }
+
+    pub fn grant_role(
+        &mut self,
+        group_id: u64,
+        actor: Principal,
+        target: Principal,
+        username: String,
+        role: String,
+    ) -> CanisterResult<HistoryEvent> {
+        self.members
+            .grant_role(actor, target, role.clone())
+            .map_err(|err| err.add_tag("group"))?;
+
+        self.member_role_history_event(
+            group_id,
+            actor,
+            target,
+            username,
+            role,
+            GroupRoleChangeKind::Add,
+        )
+    }
+
+    pub fn revoke_role(
+        &mut self,
+        group_id: u64,
+        actor: Principal,
+        target: Principal,
+        username: String,
+        role: String,
+    ) -> CanisterResult<HistoryEvent> {
+        self.members
+            .revoke_role(actor, target, role.clone())
+            .map_err(|err| err.add_tag("group"))?;
+
+        self.member_role_history_event(
+            group_id,
+            actor,
+            target,
+            username,
+            role,
+            GroupRoleChangeKind::Remove,
+        )
+    }
+
+    fn member_role_history_event(
+        &mut self,
+        group_id: u64,
+        actor: Principal,
+        principal: Principal,
+        username: String,
+        role: String,
+        kind: GroupRoleChangeKind,
+    ) -> CanisterResult<HistoryEvent> {
+        self.updated_on = time();
+
+        HistoryEvent::new(
+            actor,
+            &GroupRoleChanged::new(group_id, principal, username, vec![role], kind),
+        )
+        .map_err(|err| {
+            ApiError::serialize()
+                .add_method_name("member_role_history_event")
+                .add_message(err)
+                .add_tag("group")
+        })
+    }
+
     pub fn get_invites(&self) -> Vec<Principal> {
         self.members.invites.keys().cloned().collect()
     }
@@ -184,12 +409,123 @@ impl GroupWithMembers {
     }
 
     pub fn get_roles(&self) -> Vec<Role> {
-        // set the default protected roles
-        let mut roles = default_roles();
+        self.members.get_roles()
+    }
+
+    pub fn add_role(
+        &mut self,
+        group_id: u64,
+        actor: Principal,
+        role: PostRole,
+    ) -> CanisterResult<HistoryEvent> {
+        let role = self
+            .members
+            .add_role(actor, role)
+            .map_err(|err| err.add_tag("group"))?;
+
+        self.role_history_event(
+            group_id,
+            actor,
+            vec![role.name],
+            vec![],
+            GroupRoleUpdateKind::Create,
+        )
+    }
+
+    pub fn update_role(
+        &mut self,
+        group_id: u64,
+        actor: Principal,
+        name: String,
+        role: PostRole,
+    ) -> CanisterResult<HistoryEvent> {
+        let affected_members = self
+            .members
+            .members
+            .iter()
+            .filter(|(_, join)| join.roles.contains(&name))
+            .map(|(principal, _)| *principal)
+            .collect();
+
+        let role = self
+            .members
+            .update_role(actor, &name, role)
+            .map_err(|err| err.add_tag("group"))?;
+
+        let mut roles = vec![name];
+        if roles[0] != role.name {
+            roles.push(role.name);
+        }
 
-        // append the custom roles stored on the group
-        roles.append(&mut self.members.roles.clone());
-        roles
+        self.role_history_event(
+            group_id,
+            actor,
+            roles,
+            affected_members,
+            GroupRoleUpdateKind::Update,
+        )
+    }
+
+    pub fn remove_role(
+        &mut self,
+        group_id: u64,
+        actor: Principal,
+        name: String,
+    ) -> CanisterResult<HistoryEvent> {
+        let affected_members = self
+            .members
+            .remove_role(actor, &name)
+            .map_err(|err| err.add_tag("group"))?;
+
+        self.role_history_event(
+            group_id,
+            actor,
+            vec![name],
+            affected_members,
+            GroupRoleUpdateKind::Remove,
+        )
+    }
+
+    pub fn reorder_roles(
+        &mut self,
+        group_id: u64,
+        actor: Principal,
+        names: Vec<String>,
+    ) -> CanisterResult<HistoryEvent> {
+        let roles = self
+            .members
+            .reorder_roles(actor, names)
+            .map_err(|err| err.add_tag("group"))?;
+
+        self.role_history_event(
+            group_id,
+            actor,
+            roles.into_iter().map(|role| role.name).collect(),
+            vec![],
+            GroupRoleUpdateKind::Reorder,
+        )
+    }
+
+    fn role_history_event(
+        &mut self,
+        group_id: u64,
+        actor: Principal,
+        roles: Vec<String>,
+        affected_members: Vec<Principal>,
+        kind: GroupRoleUpdateKind,
+    ) -> CanisterResult<HistoryEvent> {
+        self.updated_on = time();
+
+        HistoryEvent::new(
+            actor,
+            &GroupRoleUpdated::new(group_id, roles, affected_members, kind),
+        )
+        .map_err(|err| {
+            ApiError::serialize()
+                .add_method_name("role_history_event")
+                .add_message(err)
+                .add_tag("group")
+        })
     }
 
     pub fn get_role_permissions(&self, role: String) -> Vec<Permission> {
@@ -220,11 +556,73 @@ impl GroupWithMembers {
     }
 
     pub fn is_banned_member(&self, member: Principal) -> bool {
+        self.members.is_banned(member)
+    }
+
+    pub fn is_muted(&self, member: Principal) -> bool {
+        self.members.is_muted(member)
+    }
+
+    pub fn restrict_member(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+        kind: RestrictionKind,
+        reason: String,
+        expires_at: Option<u64>,
+    ) -> CanisterResult<MemberRestriction> {
+        let restriction = MemberRestriction::new(kind, reason, actor, expires_at);
+        let restriction = self
+            .members
+            .restrict(actor, target, restriction)
+            .map_err(|err| err.add_tag("group"))?;
+
+        if restriction.is_kind(&RestrictionKind::Ban) {
+            self.clear_ownership_transfer_to(target);
+        }
+        self.updated_on = time();
+        Ok(restriction)
+    }
+
+    pub fn lift_restriction(
+        &mut self,
+        actor: Principal,
+        target: Principal,
+    ) -> CanisterResult<MemberRestriction> {
+        let restriction = self
+            .members
+            .lift_restriction(actor, target)
+            .map_err(|err| err.add_tag("group"))?;
+
+        self.updated_on = time();
+        Ok(restriction)
+    }
+
+    pub fn get_restrictions(
+        &self,
+        filters: Vec<MemberRestrictionFilter>,
+    ) -> Vec<MemberRestrictionEntry> {
+        self.members.get_restrictions(filters)
+    }
+
+    pub fn can(
+        &self,
+        member: Principal,
+        permission: PermissionType,
+        action: PermissionActionType,
+    ) -> bool {
+        self.members.can(member, &permission, &action)
+    }
+
+    pub fn guard_permission(
+        &self,
+        member: Principal,
+        permission: PermissionType,
+        action: PermissionActionType,
+    ) -> CanisterResult<()> {
         self.members
-            .special_members
-            .get(&member)
-            .map(|relation| relation == &RelationType::Blocked.to_string())
-            .unwrap_or_default()
+            .guard(member, &permission, &action)
+            .map_err(|err| err.add_tag("group"))
     }
 
     pub fn is_member(&self, member: Principal) -> bool {
@@ -238,6 +636,14 @@ impl GroupWithMembers {
     pub fn is_invite_only(&self) -> bool {
         self.privacy.privacy_type == PrivacyType::InviteOnly
     }
+
+    pub fn distance_km(&self, lat: f64, lng: f64) -> Option<f64> {
+        self.metadata
+            .location
+            .physical()
+            .filter(|physical| physical.has_coordinates())
+            .map(|physical| physical.distance_km(lat, lng))
+    }
 }
 
 pub type GroupEntry = (u64, GroupWithMembers);
@@ -317,7 +723,8 @@ impl GroupResponse {
             privacy_gated_type_amount: group.privacy.privacy_gated_type_amount,
             updated_on: group.updated_on,
             created_on: group.created_on,
-            boosted,
+            // expired boosts are not returned so clients don't show them as boosted
+            boosted: boosted.filter(|boost| boost.is_active(time())),
             events_count: group.events.len() as u64,
             members_count: group.members.members.len() as u64,
         }
@@ -340,6 +747,12 @@ pub enum GroupSort {
     CreatedOn(SortDirection),
     UpdatedOn(SortDirection),
     MemberCount(SortDirection),
+    Distance {
+        lat: f32,
+        lng: f32,
+    },
+    /// Boosted subjects first, ordered by remaining boost time, the boosts are passed by the caller
+    BoostedFirst(Vec<Boost>),
 }
 
 impl Default for GroupSort {
@@ -374,6 +787,19 @@ impl Sorter<u64, GroupWithMembers> for GroupSort {
                 .sort_by(|(_, a), (_, b)| a.members.members.len().cmp(&b.members.members.len())),
             MemberCount(Desc) => groups
                 .sort_by(|(_, a), (_, b)| b.members.members.len().cmp(&a.members.members.len())),
+            // groups without coordinates are placed at the end
+            Distance { lat, lng } => groups.sort_by(|(_, a), (_, b)| {
+                let a = a.distance_km(*lat as f64, *lng as f64);
+                let b = b.distance_km(*lat as f64, *lng as f64);
+                a.unwrap_or(f64::MAX).total_cmp(&b.unwrap_or(f64::MAX))
+            }),
+            // the sort is stable, so subjects without an active boost keep their order
+            BoostedFirst(boosts) => {
+                let now = time();
+                groups.sort_by_key(|(id, _)| {
+                    std::cmp::Reverse(boosted_remaining_seconds(boosts, &Subject::Group(*id), now))
+                })
+            }
         }
         groups
     }
@@ -399,6 +825,28 @@ pub enum GroupFilter {
     UpdatedOn(DateRange),
     CreatedOn(DateRange),
     OptionallyInvited(Principal),
+    Near {
+        lat: f32,
+        lng: f32,
+        radius_km: f32,
+    },
+    BoundingBox(BoundingBox),
+}
+
+impl GroupFilter {
+    /// The ids the `GeoIndex` returns for the location filters, `None` for the other filters.
+    /// The candidates still have to be checked with `matches`, for example with `StorageQueryable::filter_keys`.
+    pub fn geo_candidates(&self, index: &GeoIndex) -> Option<Vec<u64>> {
+        match self {
+            GroupFilter::Near {
+                lat,
+                lng,
+                radius_km,
+            } => Some(index.near(*lat as f64, *lng as f64, *radius_km as f64)),
+            GroupFilter::BoundingBox(bounds) => Some(index.within(bounds)),
+            _ => None,
+        }
+    }
 }
 
 impl Filter<u64, GroupWithMembers> for GroupFilter {
@@ -432,6 +880,18 @@ impl Filter<u64, GroupWithMembers> for GroupFilter {
                 true => group.is_member(*principal),
                 false => true,
             },
+            Near {
+                lat,
+                lng,
+                radius_km,
+            } => group
+                .distance_km(*lat as f64, *lng as f64)
+                .is_some_and(|distance| distance <= *radius_km as f64),
+            BoundingBox(bounds) => group
+                .metadata
+                .location
+                .physical()
+                .is_some_and(|physical| physical.is_within_bounds(bounds)),
         }
     }
 }
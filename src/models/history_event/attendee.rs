@@ -0,0 +1,36 @@
+use candid::{CandidType, Principal};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct AttendeeJoined {
+    pub event_id: u64,
+    pub group_id: Option<u64>,
+    pub principal: Principal,
+}
+
+impl AttendeeJoined {
+    pub fn new(event_id: u64, group_id: Option<u64>, principal: Principal) -> Self {
+        Self {
+            event_id,
+            group_id,
+            principal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct AttendeeLeft {
+    pub event_id: u64,
+    pub group_id: Option<u64>,
+    pub principal: Principal,
+}
+
+impl AttendeeLeft {
+    pub fn new(event_id: u64, group_id: Option<u64>, principal: Principal) -> Self {
+        Self {
+            event_id,
+            group_id,
+            principal,
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use candid::CandidType;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct EventCreated {
+    pub event_id: u64,
+    pub group_id: Option<u64>,
+    pub name: String,
+}
+
+impl EventCreated {
+    pub fn new(event_id: u64, group_id: Option<u64>, name: String) -> Self {
+        Self {
+            event_id,
+            group_id,
+            name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct EventUpdated {
+    pub event_id: u64,
+    pub group_id: Option<u64>,
+    pub name: String,
+}
+
+impl EventUpdated {
+    pub fn new(event_id: u64, group_id: Option<u64>, name: String) -> Self {
+        Self {
+            event_id,
+            group_id,
+            name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct EventCanceled {
+    pub event_id: u64,
+    pub group_id: Option<u64>,
+    pub reason: String,
+}
+
+impl EventCanceled {
+    pub fn new(event_id: u64, group_id: Option<u64>, reason: String) -> Self {
+        Self {
+            event_id,
+            group_id,
+            reason,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct EventDeleted {
+    pub event_id: u64,
+    pub group_id: Option<u64>,
+}
+
+impl EventDeleted {
+    pub fn new(event_id: u64, group_id: Option<u64>) -> Self {
+        Self { event_id, group_id }
+    }
+}
@@ -0,0 +1,15 @@
+use candid::{CandidType, Principal};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct EventTransferred {
+    pub event_id: u64,
+    pub from: Principal,
+    pub to: Principal,
+}
+
+impl EventTransferred {
+    pub fn new(event_id: u64, from: Principal, to: Principal) -> Self {
+        Self { event_id, from, to }
+    }
+}
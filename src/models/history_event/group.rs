@@ -0,0 +1,37 @@
+use candid::CandidType;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct GroupCreated {
+    pub group_id: u64,
+    pub name: String,
+}
+
+impl GroupCreated {
+    pub fn new(group_id: u64, name: String) -> Self {
+        Self { group_id, name }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct GroupUpdated {
+    pub group_id: u64,
+    pub name: String,
+}
+
+impl GroupUpdated {
+    pub fn new(group_id: u64, name: String) -> Self {
+        Self { group_id, name }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct GroupDeleted {
+    pub group_id: u64,
+}
+
+impl GroupDeleted {
+    pub fn new(group_id: u64) -> Self {
+        Self { group_id }
+    }
+}
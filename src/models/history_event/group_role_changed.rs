@@ -1,10 +1,6 @@
-use std::str::FromStr;
-
-use candid::{CandidType, Decode, Encode, Principal};
+use candid::{CandidType, Principal};
 use serde::Deserialize;
 
-use super::{history_event_kind::HistoryEventKind, HistoryEvent};
-
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
 pub enum GroupRoleChangeKind {
     Add,
@@ -38,24 +34,3 @@ impl GroupRoleChanged {
         }
     }
 }
-
-impl TryFrom<GroupRoleChanged> for HistoryEvent {
-    type Error = candid::Error;
-
-    fn try_from(value: GroupRoleChanged) -> Result<Self, Self::Error> {
-        Ok(Self {
-            kind: HistoryEventKind::GroupRoleChanged.into(),
-            data: Encode!(&value)?,
-        })
-    }
-}
-
-impl TryFrom<HistoryEvent> for GroupRoleChanged {
-    type Error = candid::Error;
-
-    fn try_from(value: HistoryEvent) -> Result<Self, Self::Error> {
-        match HistoryEventKind::from_str(&value.kind).map_err(candid::Error::msg)? {
-            HistoryEventKind::GroupRoleChanged => Decode!(&value.data, GroupRoleChanged),
-        }
-    }
-}
@@ -0,0 +1,34 @@
+use candid::{CandidType, Principal};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub enum GroupRoleUpdateKind {
+    Create,
+    Update,
+    Remove,
+    Reorder,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct GroupRoleUpdated {
+    pub group_id: u64,
+    pub roles: Vec<String>,
+    pub affected_members: Vec<Principal>,
+    pub kind: GroupRoleUpdateKind,
+}
+
+impl GroupRoleUpdated {
+    pub fn new(
+        group_id: u64,
+        roles: Vec<String>,
+        affected_members: Vec<Principal>,
+        kind: GroupRoleUpdateKind,
+    ) -> Self {
+        Self {
+            group_id,
+            roles,
+            affected_members,
+            kind,
+        }
+    }
+}
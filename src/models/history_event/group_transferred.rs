@@ -0,0 +1,15 @@
+use candid::{CandidType, Principal};
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct GroupTransferred {
+    pub group_id: u64,
+    pub from: Principal,
+    pub to: Principal,
+}
+
+impl GroupTransferred {
+    pub fn new(group_id: u64, from: Principal, to: Principal) -> Self {
+        Self { group_id, from, to }
+    }
+}
@@ -1,18 +1,113 @@
 use std::str::FromStr;
 
-use serde::Deserialize;
+use candid::{CandidType, Decode};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
-pub enum HistoryEventKind {
-    GroupRoleChanged,
-}
+use crate::models::subject::Subject;
+
+use super::*;
 
-impl std::fmt::Display for HistoryEventKind {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            HistoryEventKind::GroupRoleChanged => write!(f, "group_role_changed"),
+/// Declares the known history event kinds, every entry maps a data struct to the kind string that is stored
+/// on the `HistoryEvent` and to the field that identifies its subject. Adding a kind only requires adding
+/// the struct to this list.
+macro_rules! history_event_registry {
+    ($($kind:ident => $name:literal, $subject:ident($field:ident)),* $(,)?) => {
+        #[derive(Debug, Clone, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+        pub enum HistoryEventKind {
+            $($kind,)*
         }
-    }
+
+        impl std::fmt::Display for HistoryEventKind {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $(HistoryEventKind::$kind => write!(f, $name),)*
+                }
+            }
+        }
+
+        impl FromStr for HistoryEventKind {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($name => Ok(HistoryEventKind::$kind),)*
+                    _ => Err(format!("Unknown history event kind: {}", s)),
+                }
+            }
+        }
+
+        #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+        pub enum AnyHistoryEvent {
+            $($kind($kind),)*
+        }
+
+        impl AnyHistoryEvent {
+            pub fn kind(&self) -> HistoryEventKind {
+                match self {
+                    $(AnyHistoryEvent::$kind(_) => HistoryEventKind::$kind,)*
+                }
+            }
+        }
+
+        impl HistoryEvent {
+            pub fn decode_any(&self) -> Result<AnyHistoryEvent, candid::Error> {
+                match self.get_kind()? {
+                    $(HistoryEventKind::$kind => {
+                        Decode!(&self.data, $kind).map(AnyHistoryEvent::$kind)
+                    })*
+                }
+            }
+        }
+
+        $(
+            impl HistoryEventData for $kind {
+                const KIND: HistoryEventKind = HistoryEventKind::$kind;
+
+                fn subject(&self) -> Subject {
+                    Subject::$subject(self.$field)
+                }
+            }
+
+            impl TryFrom<HistoryEvent> for $kind {
+                type Error = candid::Error;
+
+                fn try_from(value: HistoryEvent) -> Result<Self, Self::Error> {
+                    value.decode()
+                }
+            }
+
+            impl From<$kind> for AnyHistoryEvent {
+                fn from(value: $kind) -> Self {
+                    AnyHistoryEvent::$kind(value)
+                }
+            }
+        )*
+    };
+}
+
+history_event_registry! {
+    GroupRoleChanged => "group_role_changed", Group(group_id),
+    GroupRoleUpdated => "group_role_updated", Group(group_id),
+
+    MemberJoined => "member_joined", Group(group_id),
+    MemberLeft => "member_left", Group(group_id),
+    MemberKicked => "member_kicked", Group(group_id),
+    MemberBanned => "member_banned", Group(group_id),
+    MemberInvited => "member_invited", Group(group_id),
+
+    GroupCreated => "group_created", Group(group_id),
+    GroupUpdated => "group_updated", Group(group_id),
+    GroupDeleted => "group_deleted", Group(group_id),
+    GroupTransferred => "group_transferred", Group(group_id),
+
+    EventCreated => "event_created", Event(event_id),
+    EventUpdated => "event_updated", Event(event_id),
+    EventCanceled => "event_canceled", Event(event_id),
+    EventDeleted => "event_deleted", Event(event_id),
+    EventTransferred => "event_transferred", Event(event_id),
+
+    AttendeeJoined => "attendee_joined", Event(event_id),
+    AttendeeLeft => "attendee_left", Event(event_id),
 }
 
 impl From<HistoryEventKind> for String {
@@ -20,14 +115,3 @@ impl From<HistoryEventKind> for String {
         kind.to_string()
     }
 }
-
-impl FromStr for HistoryEventKind {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "group_role_changed" => Ok(HistoryEventKind::GroupRoleChanged),
-            _ => Err(format!("Unknown history event kind: {}", s)),
-        }
-    }
-}
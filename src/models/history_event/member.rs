@@ -0,0 +1,102 @@
+use candid::{CandidType, Principal};
+use serde::Deserialize;
+
+use crate::invite_type::InviteType;
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct MemberJoined {
+    pub group_id: u64,
+    pub principal: Principal,
+}
+
+impl MemberJoined {
+    pub fn new(group_id: u64, principal: Principal) -> Self {
+        Self {
+            group_id,
+            principal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct MemberLeft {
+    pub group_id: u64,
+    pub principal: Principal,
+}
+
+impl MemberLeft {
+    pub fn new(group_id: u64, principal: Principal) -> Self {
+        Self {
+            group_id,
+            principal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct MemberKicked {
+    pub group_id: u64,
+    pub principal: Principal,
+    pub kicked_by: Principal,
+}
+
+impl MemberKicked {
+    pub fn new(group_id: u64, principal: Principal, kicked_by: Principal) -> Self {
+        Self {
+            group_id,
+            principal,
+            kicked_by,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct MemberBanned {
+    pub group_id: u64,
+    pub principal: Principal,
+    pub banned_by: Principal,
+    pub reason: String,
+    pub expires_at: Option<u64>,
+}
+
+impl MemberBanned {
+    pub fn new(
+        group_id: u64,
+        principal: Principal,
+        banned_by: Principal,
+        reason: String,
+        expires_at: Option<u64>,
+    ) -> Self {
+        Self {
+            group_id,
+            principal,
+            banned_by,
+            reason,
+            expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
+pub struct MemberInvited {
+    pub group_id: u64,
+    pub principal: Principal,
+    pub invited_by: Principal,
+    pub invite_type: InviteType,
+}
+
+impl MemberInvited {
+    pub fn new(
+        group_id: u64,
+        principal: Principal,
+        invited_by: Principal,
+        invite_type: InviteType,
+    ) -> Self {
+        Self {
+            group_id,
+            principal,
+            invited_by,
+            invite_type,
+        }
+    }
+}
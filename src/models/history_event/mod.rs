@@ -1,20 +1,148 @@
-use candid::{CandidType, Decode, Encode};
+use std::str::FromStr;
+
+use candid::{CandidType, Decode, Encode, Principal};
 use serde::Deserialize;
 
-use crate::impl_storable_for;
+use crate::{
+    helpers::time_helper::time,
+    impl_storable_for,
+    models::{date_range::DateRange, sort_direction::SortDirection, subject::Subject},
+    Filter, Sorter,
+};
 
 impl_storable_for!(HistoryEvent);
 
+pub mod attendee;
+pub mod event;
+pub mod event_transferred;
+pub mod group;
 pub mod group_role_changed;
+pub mod group_role_updated;
+pub mod group_transferred;
 pub mod history_event_kind;
+pub mod member;
 
+pub use attendee::*;
+pub use event::*;
+pub use event_transferred::*;
+pub use group::*;
 pub use group_role_changed::*;
+pub use group_role_updated::*;
+pub use group_transferred::*;
 pub use history_event_kind::*;
+pub use member::*;
+
+/// Implemented by every struct in the `history_event_registry`
+pub trait HistoryEventData: CandidType + for<'de> Deserialize<'de> {
+    const KIND: HistoryEventKind;
+
+    /// The group or event the history event belongs to
+    fn subject(&self) -> Subject;
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize)]
 pub struct HistoryEvent {
     pub kind: String,
     pub data: Vec<u8>,
+    // history events stored before these fields existed have no actor, subject or creation time
+    pub actor: Option<Principal>,
+    pub subject: Option<Subject>,
+    pub created_at: Option<u64>,
+}
+
+impl HistoryEvent {
+    pub fn new<T: HistoryEventData>(actor: Principal, data: &T) -> Result<Self, candid::Error> {
+        Ok(Self {
+            kind: T::KIND.into(),
+            data: Encode!(data)?,
+            actor: Some(actor),
+            subject: Some(data.subject()),
+            created_at: Some(time()),
+        })
+    }
+
+    pub fn actor(&self) -> Option<Principal> {
+        self.actor
+    }
+
+    pub fn subject(&self) -> Option<Subject> {
+        self.subject.clone()
+    }
+
+    /// `0` for history events stored without a creation time
+    pub fn created_at(&self) -> u64 {
+        self.created_at.unwrap_or_default()
+    }
+
+    pub fn decode<T: HistoryEventData>(&self) -> Result<T, candid::Error> {
+        let kind = self.get_kind()?;
+        if kind != T::KIND {
+            return Err(candid::Error::msg(format!(
+                "Unexpected history event kind: {kind}"
+            )));
+        }
+
+        Decode!(&self.data, T)
+    }
+
+    pub fn get_kind(&self) -> Result<HistoryEventKind, candid::Error> {
+        HistoryEventKind::from_str(&self.kind).map_err(candid::Error::msg)
+    }
+
+    pub fn is_kind(&self, kind: &HistoryEventKind) -> bool {
+        self.get_kind().is_ok_and(|k| k == *kind)
+    }
 }
 
 pub type HistoryEventEntry = (u64, HistoryEvent);
+
+#[derive(Clone, Debug, CandidType, Deserialize, Default)]
+pub enum HistoryEventFilter {
+    #[default]
+    None,
+    Subject(Subject),
+    Actor(Principal),
+    Kind(HistoryEventKind),
+    CreatedAt(DateRange),
+}
+
+impl Filter<u64, HistoryEvent> for HistoryEventFilter {
+    fn matches(&self, _id: &u64, history_event: &HistoryEvent) -> bool {
+        use HistoryEventFilter::*;
+        match self {
+            None => true,
+            Subject(subject) => history_event.subject.as_ref() == Some(subject),
+            Actor(actor) => history_event.actor == Some(*actor),
+            Kind(kind) => history_event.is_kind(kind),
+            CreatedAt(date_range) => date_range.is_within(history_event.created_at()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub enum HistoryEventSort {
+    CreatedAt(SortDirection),
+}
+
+impl Default for HistoryEventSort {
+    fn default() -> Self {
+        HistoryEventSort::CreatedAt(SortDirection::Desc)
+    }
+}
+
+impl Sorter<u64, HistoryEvent> for HistoryEventSort {
+    fn sort(&self, history_events: Vec<(u64, HistoryEvent)>) -> Vec<(u64, HistoryEvent)> {
+        let mut history_events = history_events;
+        use HistoryEventSort::*;
+        use SortDirection::*;
+        match self {
+            // the id breaks ties so events created in the same call keep their insertion order
+            CreatedAt(direction) => match direction {
+                Asc => history_events.sort_by_key(|(id, event)| (event.created_at(), *id)),
+                Desc => history_events
+                    .sort_by_key(|(id, event)| std::cmp::Reverse((event.created_at(), *id))),
+            },
+        }
+        history_events
+    }
+}
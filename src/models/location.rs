@@ -3,6 +3,8 @@ use std::fmt;
 use candid::CandidType;
 use serde::{Deserialize, Serialize};
 
+use crate::helpers::geo::{geohash_encode, haversine_km, is_valid_coordinate, wrap_longitude};
+
 #[derive(Clone, Debug, Serialize, CandidType, Deserialize, Default)]
 pub enum Location {
     #[default]
@@ -30,6 +32,16 @@ pub struct MultiLocation {
     pub digital: String,
 }
 
+impl Location {
+    pub fn physical(&self) -> Option<&PhysicalLocation> {
+        match self {
+            Location::Physical(physical) => Some(physical),
+            Location::MultiLocation(multi) => Some(&multi.physical),
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Display for Location {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use Location::*;
@@ -50,3 +62,73 @@ pub struct PhysicalLocation {
     pub lattitude: f32,
     pub address: Address,
 }
+
+impl PhysicalLocation {
+    pub fn lat(&self) -> f64 {
+        self.lattitude as f64
+    }
+
+    pub fn lng(&self) -> f64 {
+        self.longtitude as f64
+    }
+
+    pub fn has_coordinates(&self) -> bool {
+        // the frontend stores `0, 0` when no coordinates are picked
+        is_valid_coordinate(self.lat(), self.lng()) && (self.lat() != 0.0 || self.lng() != 0.0)
+    }
+
+    pub fn distance_km(&self, lat: f64, lng: f64) -> f64 {
+        haversine_km(self.lat(), self.lng(), lat, lng)
+    }
+
+    pub fn distance_to(&self, other: &PhysicalLocation) -> f64 {
+        self.distance_km(other.lat(), other.lng())
+    }
+
+    pub fn is_within_radius(&self, lat: f64, lng: f64, radius_km: f64) -> bool {
+        self.has_coordinates() && self.distance_km(lat, lng) <= radius_km
+    }
+
+    pub fn is_within_bounds(&self, bounds: &BoundingBox) -> bool {
+        self.has_coordinates() && bounds.contains(self.lat(), self.lng())
+    }
+
+    pub fn geohash(&self, precision: usize) -> String {
+        geohash_encode(self.lat(), self.lng(), precision)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, CandidType, Serialize, Deserialize, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f32,
+    pub min_lng: f32,
+    pub max_lat: f32,
+    pub max_lng: f32,
+}
+
+impl BoundingBox {
+    pub fn new(min_lat: f32, min_lng: f32, max_lat: f32, max_lng: f32) -> Self {
+        Self {
+            min_lat,
+            min_lng,
+            max_lat,
+            max_lng,
+        }
+    }
+
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.min_lng > self.max_lng
+    }
+
+    pub fn contains(&self, lat: f64, lng: f64) -> bool {
+        let lng = wrap_longitude(lng);
+        if lat < self.min_lat as f64 || lat > self.max_lat as f64 {
+            return false;
+        }
+
+        match self.crosses_antimeridian() {
+            true => lng >= self.min_lng as f64 || lng <= self.max_lng as f64,
+            false => lng >= self.min_lng as f64 && lng <= self.max_lng as f64,
+        }
+    }
+}
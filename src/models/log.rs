@@ -1,6 +1,9 @@
 use candid::{CandidType, Deserialize, Principal};
 
-use crate::impl_storable_for;
+use crate::{
+    helpers::time_helper::{format_iso8601, time},
+    impl_storable_for,
+};
 
 pub type Timestamp = u64;
 pub type LogId = u64;
@@ -61,7 +64,7 @@ impl Logger {
             source: post_log.source,
             data: post_log.data,
             principal: None,
-            created_on: ic_cdk::api::time(),
+            created_on: time(),
         }
     }
 
@@ -80,3 +83,16 @@ impl Logger {
         }
     }
 }
+
+impl From<(LogId, Logger)> for HumanReadableLogger {
+    fn from((id, log): (LogId, Logger)) -> Self {
+        Self {
+            id,
+            description: log.description,
+            source: log.source,
+            principal: log.principal.map(|principal| principal.to_string()),
+            data: log.data,
+            created_on: format_iso8601(log.created_on),
+        }
+    }
+}
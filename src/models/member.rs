@@ -1,14 +1,18 @@
 use candid::{CandidType, Deserialize};
-use ic_cdk::api::time;
 use serde::Serialize;
 
-use crate::misc::role_misc::{ADMIN_ROLE, MEMBER_ROLE, MODERATOR_ROLE, OWNER_ROLE};
+use crate::{
+    helpers::time_helper::time,
+    misc::role_misc::{ADMIN_ROLE, MEMBER_ROLE, MODERATOR_ROLE, OWNER_ROLE},
+};
 
 use super::invite_type::InviteType;
 
 #[derive(CandidType, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Join {
     pub roles: Vec<String>,
+    pub rsvp: Option<RsvpStatus>,
+    pub checked_in_at: Option<u64>,
     pub updated_at: u64,
     pub created_at: u64,
 }
@@ -17,19 +21,26 @@ impl Default for Join {
     fn default() -> Self {
         Self {
             roles: vec![MEMBER_ROLE.into()],
+            rsvp: None,
+            checked_in_at: None,
             updated_at: time(),
             created_at: time(),
         }
     }
 }
 
+#[derive(CandidType, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RsvpStatus {
+    Going,
+    Maybe,
+    Declined,
+}
+
 impl Join {
     pub fn set_owner_role(&mut self) -> Self {
-        Self {
-            roles: vec![(OWNER_ROLE.into())],
-            updated_at: time(),
-            created_at: time(),
-        }
+        self.roles = vec![OWNER_ROLE.into()];
+        self.updated_at = time();
+        self.clone()
     }
 
     pub fn has_owner_role(&self) -> bool {
@@ -37,11 +48,9 @@ impl Join {
     }
 
     pub fn set_admin_role(&mut self) -> Self {
-        Self {
-            roles: vec![(ADMIN_ROLE.into())],
-            updated_at: time(),
-            created_at: time(),
-        }
+        self.roles = vec![ADMIN_ROLE.into()];
+        self.updated_at = time();
+        self.clone()
     }
 
     pub fn has_admin_role(&self) -> bool {
@@ -49,11 +58,9 @@ impl Join {
     }
 
     pub fn set_moderator_role(&mut self) -> Self {
-        Self {
-            roles: vec![(MODERATOR_ROLE.into())],
-            updated_at: time(),
-            created_at: time(),
-        }
+        self.roles = vec![MODERATOR_ROLE.into()];
+        self.updated_at = time();
+        self.clone()
     }
 
     pub fn has_moderator_role(&self) -> bool {
@@ -61,11 +68,9 @@ impl Join {
     }
 
     pub fn set_member_role(&mut self) -> Self {
-        Self {
-            roles: vec![(MEMBER_ROLE.into())],
-            updated_at: time(),
-            created_at: time(),
-        }
+        self.roles = vec![MEMBER_ROLE.into()];
+        self.updated_at = time();
+        self.clone()
     }
 
     pub fn has_member_role(&self) -> bool {
@@ -84,6 +89,50 @@ impl Join {
         self.roles.retain(|r| r != &role);
         self.updated_at = time();
     }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn grant_role(&mut self, role: String) {
+        if self.has_role(&role) {
+            return;
+        }
+        self.roles.push(role);
+        self.updated_at = time();
+    }
+
+    /// Joining an event without responding counts as going
+    pub fn get_rsvp(&self) -> RsvpStatus {
+        self.rsvp.unwrap_or(RsvpStatus::Going)
+    }
+
+    pub fn set_rsvp(&mut self, rsvp: RsvpStatus) {
+        self.rsvp = Some(rsvp);
+        self.updated_at = time();
+    }
+
+    pub fn is_checked_in(&self) -> bool {
+        self.checked_in_at.is_some()
+    }
+
+    pub fn check_in(&mut self) {
+        self.checked_in_at = Some(time());
+        self.updated_at = time();
+    }
+
+    pub fn undo_check_in(&mut self) {
+        self.checked_in_at = None;
+        self.updated_at = time();
+    }
+
+    pub fn revoke_role(&mut self, role: String) {
+        self.remove_role(role);
+        // every member keeps at least the default member role
+        if self.roles.is_empty() {
+            self.roles.push(MEMBER_ROLE.into());
+        }
+    }
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
@@ -0,0 +1,100 @@
+use std::fmt;
+
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::time_helper::time, Filter};
+
+#[derive(CandidType, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum RestrictionKind {
+    // removed from the group or event and can not join again
+    Ban,
+    // can not write in the chat
+    Mute,
+    // can only read until the restriction expires
+    Timeout,
+}
+
+impl fmt::Display for RestrictionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use RestrictionKind::*;
+        match self {
+            Ban => write!(f, "ban"),
+            Mute => write!(f, "mute"),
+            Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+#[derive(CandidType, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MemberRestriction {
+    pub kind: RestrictionKind,
+    pub reason: String,
+    pub created_by: Principal,
+    pub created_at: u64,
+    pub expires_at: Option<u64>,
+}
+
+impl MemberRestriction {
+    pub fn new(
+        kind: RestrictionKind,
+        reason: String,
+        created_by: Principal,
+        expires_at: Option<u64>,
+    ) -> Self {
+        Self {
+            kind,
+            reason,
+            created_by,
+            created_at: time(),
+            expires_at,
+        }
+    }
+
+    // bans stored as a `RelationType::Blocked` special member before the restrictions were typed
+    pub fn legacy_ban() -> Self {
+        Self {
+            kind: RestrictionKind::Ban,
+            reason: Default::default(),
+            created_by: Principal::anonymous(),
+            created_at: Default::default(),
+            expires_at: None,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| time() >= expires_at)
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.is_expired()
+    }
+
+    pub fn is_kind(&self, kind: &RestrictionKind) -> bool {
+        self.kind == *kind && self.is_active()
+    }
+}
+
+pub type MemberRestrictionEntry = (Principal, MemberRestriction);
+
+#[derive(Clone, Debug, CandidType, Deserialize, Default)]
+pub enum MemberRestrictionFilter {
+    #[default]
+    None,
+    Kind(RestrictionKind),
+    IsActive(bool),
+    CreatedBy(Principal),
+}
+
+impl Filter<Principal, MemberRestriction> for MemberRestrictionFilter {
+    fn matches(&self, _key: &Principal, restriction: &MemberRestriction) -> bool {
+        use MemberRestrictionFilter::*;
+        match self {
+            None => true,
+            Kind(kind) => restriction.kind == *kind,
+            IsActive(is_active) => restriction.is_active() == *is_active,
+            CreatedBy(principal) => restriction.created_by == *principal,
+        }
+    }
+}
@@ -3,18 +3,26 @@ pub mod application_role;
 pub mod asset;
 pub mod boosted;
 pub mod date_range;
+pub mod digest;
 pub mod event_with_attendees;
 pub mod filter_type;
+pub mod gate;
 pub mod general_structs;
+pub mod geo_index;
 pub mod group_with_members;
 pub mod history_event;
 pub mod location;
 pub mod notification;
+pub mod notification_preferences;
 pub mod old_member;
+pub mod ownership_transfer;
 pub mod paged_response;
 pub mod privacy;
 pub mod profile_structs;
 pub mod profile_with_refs;
+pub mod recipient_resolver;
+pub mod recurrence;
+pub mod reminder;
 pub mod role;
 pub mod sort_direction;
 pub mod storage;
@@ -33,6 +41,7 @@ pub mod friend_request;
 pub mod group;
 pub mod invite_type;
 pub mod member;
+pub mod member_restriction;
 pub mod profile;
 pub mod report;
 pub mod topic;
@@ -42,6 +51,7 @@ pub mod http_types;
 pub mod member_collection;
 pub mod neuron;
 pub mod subject;
+pub mod ticket;
 pub mod transaction_data;
 pub mod user_notifications;
 pub mod websocket_message;
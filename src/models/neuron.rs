@@ -1,30 +1,30 @@
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct NeuronId {
     pub id: Vec<u8>,
 }
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct ListNeurons {
     pub of_principal: Option<Principal>,
     pub limit: u32,
     pub start_page_at: Option<NeuronId>,
 }
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct ListNeuronsResponse {
     pub neurons: Vec<Neuron>,
 }
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct NeuronPermission {
     pub principal: Option<Principal>,
     pub permission_type: Vec<i32>,
 }
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct Neuron {
     pub id: Option<NeuronId>,
     pub staked_maturity_e8s_equivalent: Option<u64>,
@@ -43,30 +43,30 @@ pub struct Neuron {
     pub neuron_fees_e8s: u64,
 }
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct DisburseMaturityInProgress {
     pub timestamp_of_disbursement_seconds: u64,
     pub amount_e8s: u64,
     pub account_to_disburse_to: Option<Account>,
 }
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct Account {
     pub owner: Option<candid::Principal>,
     pub subaccount: Option<Subaccount>,
 }
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct Followees {
     pub followees: Vec<NeuronId>,
 }
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub struct Subaccount {
     pub subaccount: Vec<u8>,
 }
 
-#[derive(CandidType, Deserialize, Serialize)]
+#[derive(CandidType, Clone, Debug, Deserialize, Serialize)]
 pub enum DissolveState {
     DissolveDelaySeconds(u64),
     WhenDissolvedTimestampSeconds(u64),
@@ -1,16 +1,21 @@
 use candid::{CandidType, Principal};
-use ic_cdk::{api::time, caller};
+use ic_cdk::caller;
 use serde::{Deserialize, Serialize};
 
 use candid::{Decode, Encode};
 
-use crate::{impl_storable_for, Filter, Sorter};
+use crate::{helpers::time_helper::time, impl_storable_for, CanisterResult, Filter, Sorter};
 
 use super::{
+    api_error::ApiError,
     attendee::{InviteAttendeeResponse, JoinedAttendeeResponse},
+    digest::DigestNotification,
     friend_request::FriendRequestResponse,
     old_member::{InviteMemberResponse, JoinedMemberResponse},
+    ownership_transfer::OwnershipTransferResponse,
+    recipient_resolver::RecipientResolver,
     sort_direction::SortDirection,
+    subject::Subject,
     transaction_data::{TransactionCompleteData, TransactionData},
     user_notifications::UserNotificationData,
 };
@@ -45,6 +50,7 @@ impl Notification {
         }
     }
 
+    #[deprecated(note = "use `process` which validates the transition and the processor")]
     pub fn mark_as_accepted(&mut self, is_accepted: bool, notification_type: NotificationType) {
         self.is_accepted = Some(is_accepted);
         self.is_actionable = false;
@@ -53,6 +59,58 @@ impl Notification {
         self.notification_type = notification_type;
     }
 
+    /// Accepts or declines an actionable request and returns the answering notification type.
+    /// Only a recipient of the request as resolved by the `resolver` can process it, that is the invitee
+    /// for owner requests and the owner or an admin of the group or event for user requests.
+    /// The resolver has to be built for the group or event the request is about, the first of its `subjects`,
+    /// and without a context for friend requests.
+    pub fn process(
+        &mut self,
+        processor: Principal,
+        action: NotificationAction,
+        resolver: &RecipientResolver,
+    ) -> CanisterResult<NotificationType> {
+        if !self.is_actionable {
+            return Err(ApiError::bad_request()
+                .add_method_name("process")
+                .add_message("Notification is already processed")
+                .add_tag("notification"));
+        }
+
+        let notification_type = self.notification_type.transition(action).ok_or_else(|| {
+            ApiError::bad_request()
+                .add_method_name("process")
+                .add_message("Notification type can not be accepted or declined")
+                .add_info(self.notification_type.variant_name())
+                .add_tag("notification")
+        })?;
+
+        // the recipients are only meaningful for the group or event of the request
+        let subject = self.notification_type.subjects().into_iter().next();
+        if resolver.subject() != subject {
+            return Err(ApiError::bad_request()
+                .add_method_name("process")
+                .add_message("Resolver is not built for the subject of the notification")
+                .add_info(format!("{:?}", resolver.subject()))
+                .add_tag("notification"));
+        }
+
+        if !resolver.recipients(self).contains(&processor) {
+            return Err(ApiError::unauthorized()
+                .add_method_name("process")
+                .add_message("Principal is not allowed to process the notification")
+                .add_info(processor)
+                .add_tag("notification"));
+        }
+
+        self.is_accepted = Some(action == NotificationAction::Accept);
+        self.is_actionable = false;
+        self.processed_by = Some(processor);
+        self.updated_at = time();
+        self.notification_type = notification_type.clone();
+        Ok(notification_type)
+    }
+
     pub fn set_metadata(&mut self, metadata: String) {
         self.metadata = Some(metadata);
         self.updated_at = time();
@@ -143,6 +201,9 @@ impl Filter<u64, Notification> for NotificationFilter {
                         NotificationType::Multisig(_)
                     )
                 }
+                NotificationType::Digest(_) => {
+                    matches!(notification.notification_type, NotificationType::Digest(_))
+                }
             },
             Actionable(actionable) => notification.is_actionable == *actionable,
             ProcessedBy(processed_by) => {
@@ -166,6 +227,259 @@ pub enum NotificationType {
     Event(EventNotificationType),
     Transaction(TransactionNotificationType),
     Multisig(MultisigNotificationType),
+    // several similar notifications coalesced into one
+    Digest(DigestNotification),
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotificationAction {
+    Accept,
+    Decline,
+}
+
+/// The top level kind of a `NotificationType`, used for preferences and unread counts
+#[derive(
+    CandidType, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum NotificationCategory {
+    Relation,
+    Group,
+    Event,
+    Transaction,
+    Multisig,
+    Digest,
+}
+
+impl NotificationType {
+    pub fn category(&self) -> NotificationCategory {
+        match self {
+            NotificationType::Relation(_) => NotificationCategory::Relation,
+            NotificationType::Group(_) => NotificationCategory::Group,
+            NotificationType::Event(_) => NotificationCategory::Event,
+            NotificationType::Transaction(_) => NotificationCategory::Transaction,
+            NotificationType::Multisig(_) => NotificationCategory::Multisig,
+            NotificationType::Digest(_) => NotificationCategory::Digest,
+        }
+    }
+
+    /// Name of the inner variant, for example `UserJoinGroup`. The names are only unique within a category.
+    pub fn variant_name(&self) -> &'static str {
+        use NotificationType::*;
+        match self {
+            Relation(relation) => {
+                use RelationNotificationType::*;
+                match relation {
+                    FriendRequest(_) => "FriendRequest",
+                    FriendRequestAccept(_) => "FriendRequestAccept",
+                    FriendRequestDecline(_) => "FriendRequestDecline",
+                    FriendRequestRemove(_) => "FriendRequestRemove",
+                    FriendRemove(_) => "FriendRemove",
+                    BlockUser(_) => "BlockUser",
+                    FriendRequestReminder(_) => "FriendRequestReminder",
+                }
+            }
+            Group(group) => {
+                use GroupNotificationType::*;
+                match group {
+                    JoinGroupUserRequest(_) => "JoinGroupUserRequest",
+                    JoinGroupUserRequestAccept(_) => "JoinGroupUserRequestAccept",
+                    JoinGroupUserRequestDecline(_) => "JoinGroupUserRequestDecline",
+                    JoinGroupOwnerRequest(_) => "JoinGroupOwnerRequest",
+                    JoinGroupOwnerRequestAccept(_) => "JoinGroupOwnerRequestAccept",
+                    JoinGroupOwnerRequestDecline(_) => "JoinGroupOwnerRequestDecline",
+                    RoleAssignByOwner(_) => "RoleAssignByOwner",
+                    RemoveInviteByOwner(_) => "RemoveInviteByOwner",
+                    RemoveMemberByOwner(_) => "RemoveMemberByOwner",
+                    UserLeaveGroup(_) => "UserLeaveGroup",
+                    UserJoinGroup(_) => "UserJoinGroup",
+                    GroupReminder(_) => "GroupReminder",
+                    OwnershipTransferRequest(_) => "OwnershipTransferRequest",
+                    OwnershipTransferAccept(_) => "OwnershipTransferAccept",
+                    OwnershipTransferDecline(_) => "OwnershipTransferDecline",
+                }
+            }
+            Event(event) => {
+                use EventNotificationType::*;
+                match event {
+                    JoinEventUserRequest(_) => "JoinEventUserRequest",
+                    JoinEventUserRequestAccept(_) => "JoinEventUserRequestAccept",
+                    JoinEventUserRequestDecline(_) => "JoinEventUserRequestDecline",
+                    JoinEventOwnerRequest(_) => "JoinEventOwnerRequest",
+                    JoinEventOwnerRequestAccept(_) => "JoinEventOwnerRequestAccept",
+                    JoinEventOwnerRequestDecline(_) => "JoinEventOwnerRequestDecline",
+                    RoleAssignByOwner(_) => "RoleAssignByOwner",
+                    RemoveInviteByOwner(_) => "RemoveInviteByOwner",
+                    RemoveAttendeeByOwner(_) => "RemoveAttendeeByOwner",
+                    UserJoinEvent(_) => "UserJoinEvent",
+                    UserLeaveEvent(_) => "UserLeaveEvent",
+                    EventReminder(_) => "EventReminder",
+                    WaitlistPromoted(_) => "WaitlistPromoted",
+                    OwnershipTransferRequest(_) => "OwnershipTransferRequest",
+                    OwnershipTransferAccept(_) => "OwnershipTransferAccept",
+                    OwnershipTransferDecline(_) => "OwnershipTransferDecline",
+                }
+            }
+            Transaction(transaction) => match transaction {
+                TransactionNotificationType::SingleTransaction(_) => "SingleTransaction",
+                TransactionNotificationType::TransactionsComplete(_) => "TransactionsComplete",
+            },
+            Multisig(multisig) => {
+                use MultisigNotificationType::*;
+                match multisig {
+                    WhitelistNotice(_) => "WhitelistNotice",
+                    NewProposal(_) => "NewProposal",
+                    ProposalAccept(_) => "ProposalAccept",
+                    ProposalDecline(_) => "ProposalDecline",
+                    ProposalStatusUpdate(_) => "ProposalStatusUpdate",
+                }
+            }
+            Digest(_) => "Digest",
+        }
+    }
+
+    /// The answer to a request, `None` when the notification type is not a request
+    pub fn transition(&self, action: NotificationAction) -> Option<NotificationType> {
+        use NotificationAction::*;
+        let notification_type = match self {
+            NotificationType::Relation(relation) => {
+                use RelationNotificationType::*;
+                NotificationType::Relation(match (relation, action) {
+                    (FriendRequest(request), Accept) => FriendRequestAccept(request.clone()),
+                    (FriendRequest(request), Decline) => FriendRequestDecline(request.clone()),
+                    _ => return None,
+                })
+            }
+            NotificationType::Group(group) => {
+                use GroupNotificationType::*;
+                NotificationType::Group(match (group, action) {
+                    (JoinGroupUserRequest(response), Accept) => {
+                        JoinGroupUserRequestAccept(response.clone())
+                    }
+                    (JoinGroupUserRequest(response), Decline) => {
+                        JoinGroupUserRequestDecline(response.clone())
+                    }
+                    (JoinGroupOwnerRequest(response), Accept) => {
+                        JoinGroupOwnerRequestAccept(response.clone())
+                    }
+                    (JoinGroupOwnerRequest(response), Decline) => {
+                        JoinGroupOwnerRequestDecline(response.clone())
+                    }
+                    (OwnershipTransferRequest(response), Accept) => {
+                        OwnershipTransferAccept(response.clone())
+                    }
+                    (OwnershipTransferRequest(response), Decline) => {
+                        OwnershipTransferDecline(response.clone())
+                    }
+                    _ => return None,
+                })
+            }
+            NotificationType::Event(event) => {
+                use EventNotificationType::*;
+                NotificationType::Event(match (event, action) {
+                    (JoinEventUserRequest(response), Accept) => {
+                        JoinEventUserRequestAccept(response.clone())
+                    }
+                    (JoinEventUserRequest(response), Decline) => {
+                        JoinEventUserRequestDecline(response.clone())
+                    }
+                    (JoinEventOwnerRequest(response), Accept) => {
+                        JoinEventOwnerRequestAccept(response.clone())
+                    }
+                    (JoinEventOwnerRequest(response), Decline) => {
+                        JoinEventOwnerRequestDecline(response.clone())
+                    }
+                    (OwnershipTransferRequest(response), Accept) => {
+                        OwnershipTransferAccept(response.clone())
+                    }
+                    (OwnershipTransferRequest(response), Decline) => {
+                        OwnershipTransferDecline(response.clone())
+                    }
+                    _ => return None,
+                })
+            }
+            _ => return None,
+        };
+        Some(notification_type)
+    }
+
+    pub fn is_request(&self) -> bool {
+        self.transition(NotificationAction::Accept).is_some()
+    }
+
+    /// Join and leave notifications that are coalesced into a digest for recipients that don't want
+    /// them immediately
+    pub fn is_digestible(&self) -> bool {
+        matches!(
+            self,
+            NotificationType::Group(
+                GroupNotificationType::UserJoinGroup(_) | GroupNotificationType::UserLeaveGroup(_)
+            ) | NotificationType::Event(
+                EventNotificationType::UserJoinEvent(_) | EventNotificationType::UserLeaveEvent(_)
+            )
+        )
+    }
+
+    /// The groups and events the notification is about, event notifications include the group of the event
+    /// except for reminders and ownership transfers which only carry the event id
+    pub fn subjects(&self) -> Vec<Subject> {
+        use NotificationType::*;
+        match self {
+            Group(group) => {
+                use GroupNotificationType::*;
+                match group {
+                    JoinGroupUserRequest(response)
+                    | JoinGroupUserRequestAccept(response)
+                    | JoinGroupUserRequestDecline(response)
+                    | JoinGroupOwnerRequest(response)
+                    | JoinGroupOwnerRequestAccept(response)
+                    | JoinGroupOwnerRequestDecline(response)
+                    | RemoveInviteByOwner(response) => vec![Subject::Group(response.group_id)],
+                    RoleAssignByOwner(response) | RemoveMemberByOwner(response) => {
+                        vec![Subject::Group(response.group_id)]
+                    }
+                    UserLeaveGroup(group_id)
+                    | UserJoinGroup(group_id)
+                    | GroupReminder(group_id) => {
+                        vec![Subject::Group(*group_id)]
+                    }
+                    OwnershipTransferRequest(response)
+                    | OwnershipTransferAccept(response)
+                    | OwnershipTransferDecline(response) => vec![response.subject.clone()],
+                }
+            }
+            Event(event) => {
+                use EventNotificationType::*;
+                match event {
+                    JoinEventUserRequest(response)
+                    | JoinEventUserRequestAccept(response)
+                    | JoinEventUserRequestDecline(response)
+                    | JoinEventOwnerRequest(response)
+                    | JoinEventOwnerRequestAccept(response)
+                    | JoinEventOwnerRequestDecline(response)
+                    | RemoveInviteByOwner(response) => vec![
+                        Subject::Event(response.event_id),
+                        Subject::Group(response.group_id),
+                    ],
+                    RoleAssignByOwner(response)
+                    | RemoveAttendeeByOwner(response)
+                    | WaitlistPromoted(response) => vec![
+                        Subject::Event(response.event_id),
+                        Subject::Group(response.group_id),
+                    ],
+                    // (event_id, group_id) like the attendee responses
+                    UserJoinEvent((event_id, group_id)) | UserLeaveEvent((event_id, group_id)) => {
+                        vec![Subject::Event(*event_id), Subject::Group(*group_id)]
+                    }
+                    EventReminder(event_id) => vec![Subject::Event(*event_id)],
+                    OwnershipTransferRequest(response)
+                    | OwnershipTransferAccept(response)
+                    | OwnershipTransferDecline(response) => vec![response.subject.clone()],
+                }
+            }
+            Digest(digest) => vec![digest.subject.clone()],
+            Relation(_) | Transaction(_) | Multisig(_) => vec![],
+        }
+    }
 }
 
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
@@ -213,6 +527,11 @@ pub enum GroupNotificationType {
     UserLeaveGroup(u64),
     UserJoinGroup(u64),
     GroupReminder(u64),
+
+    // owner wants to hand over the group to another user
+    OwnershipTransferRequest(OwnershipTransferResponse),
+    OwnershipTransferAccept(OwnershipTransferResponse),
+    OwnershipTransferDecline(OwnershipTransferResponse),
 }
 
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
@@ -234,6 +553,13 @@ pub enum EventNotificationType {
     UserJoinEvent((u64, u64)),
     UserLeaveEvent((u64, u64)),
     EventReminder(u64),
+    // a spot opened up and the user moved from the waitlist to the attendees
+    WaitlistPromoted(JoinedAttendeeResponse),
+
+    // owner wants to hand over the event to another user
+    OwnershipTransferRequest(OwnershipTransferResponse),
+    OwnershipTransferAccept(OwnershipTransferResponse),
+    OwnershipTransferDecline(OwnershipTransferResponse),
 }
 
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
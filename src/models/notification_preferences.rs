@@ -0,0 +1,209 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helpers::time_helper::{parse_utc_offset, time, NANOSECONDS_PER_SECOND},
+    CanisterResult,
+};
+
+use super::{
+    api_error::ApiError,
+    digest::DigestMode,
+    notification::{Notification, NotificationCategory},
+    profile_with_refs::ProfileWithRefs,
+    subject::Subject,
+};
+
+pub const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// A daily window in which notifications are stored without being pushed, the window wraps around
+/// midnight when `start_minute` is after `end_minute`
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct QuietHours {
+    /// Minutes after midnight in the timezone
+    pub start_minute: u32,
+    pub end_minute: u32,
+    /// Fixed offset or `Etc/GMT` timezone, `validate` rejects timezones that can not be resolved
+    pub timezone: Option<String>,
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> CanisterResult<()> {
+        if self.start_minute >= MINUTES_PER_DAY || self.end_minute >= MINUTES_PER_DAY {
+            return Err(ApiError::bad_request()
+                .add_method_name("validate")
+                .add_message("Quiet hours must be between 0 and 1439 minutes after midnight")
+                .add_tag("notification_preferences"));
+        }
+
+        // `is_quiet` would silently fall back to UTC for a timezone it can not resolve
+        if let Some(timezone) = &self.timezone {
+            if parse_utc_offset(timezone).is_none() {
+                return Err(ApiError::bad_request()
+                    .add_method_name("validate")
+                    .add_message("Timezone must be a fixed offset like +02:00 or Etc/GMT-2")
+                    .add_info(timezone)
+                    .add_tag("notification_preferences"));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_quiet(&self, now: u64) -> bool {
+        let offset = self
+            .timezone
+            .as_deref()
+            .and_then(parse_utc_offset)
+            .unwrap_or_default();
+
+        let seconds = (now / NANOSECONDS_PER_SECOND) as i64 + offset;
+        let minute = (seconds.rem_euclid(MINUTES_PER_DAY as i64 * 60) / 60) as u32;
+
+        match self.start_minute <= self.end_minute {
+            true => minute >= self.start_minute && minute < self.end_minute,
+            false => minute >= self.start_minute || minute < self.end_minute,
+        }
+    }
+}
+
+/// Everything is delivered by default, the lists only hold what the user muted
+#[derive(CandidType, Clone, Default, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct NotificationPreferences {
+    pub muted_categories: Vec<NotificationCategory>,
+    /// Variants by category and name, for example `(Group, "UserJoinGroup")`
+    pub muted_variants: Vec<(NotificationCategory, String)>,
+    /// Muting a group also mutes the event notifications that name the group, event reminders and event ownership
+    /// transfers only name the event and are muted per event
+    pub muted_subjects: Vec<Subject>,
+    pub quiet_hours: Option<QuietHours>,
+    /// How join and leave notifications are delivered, see `DigestQueue::add`
+    pub digest_mode: DigestMode,
+}
+
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub enum DeliveryDecision {
+    Deliver,
+    /// Store the notification without pushing it to the user
+    DeliverSilently,
+    Skip(SkipReason),
+}
+
+impl DeliveryDecision {
+    pub fn is_stored(&self) -> bool {
+        !matches!(self, DeliveryDecision::Skip(_))
+    }
+
+    pub fn is_pushed(&self) -> bool {
+        matches!(self, DeliveryDecision::Deliver)
+    }
+}
+
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub enum SkipReason {
+    CategoryMuted(NotificationCategory),
+    VariantMuted(String),
+    SubjectMuted(Subject),
+}
+
+impl NotificationPreferences {
+    pub fn validate(&self) -> CanisterResult<()> {
+        match &self.quiet_hours {
+            Some(quiet_hours) => quiet_hours.validate(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn set_category_enabled(&mut self, category: NotificationCategory, enabled: bool) {
+        self.muted_categories.retain(|muted| muted != &category);
+        if !enabled {
+            self.muted_categories.push(category);
+        }
+    }
+
+    pub fn set_variant_enabled(
+        &mut self,
+        category: NotificationCategory,
+        variant_name: impl Into<String>,
+        enabled: bool,
+    ) {
+        let variant = (category, variant_name.into());
+        self.muted_variants.retain(|muted| muted != &variant);
+        if !enabled {
+            self.muted_variants.push(variant);
+        }
+    }
+
+    pub fn mute_subject(&mut self, subject: Subject) {
+        if !self.muted_subjects.contains(&subject) {
+            self.muted_subjects.push(subject);
+        }
+    }
+
+    pub fn unmute_subject(&mut self, subject: &Subject) {
+        self.muted_subjects.retain(|muted| muted != subject);
+    }
+
+    pub fn is_category_enabled(&self, category: NotificationCategory) -> bool {
+        !self.muted_categories.contains(&category)
+    }
+
+    pub fn is_variant_enabled(&self, category: NotificationCategory, variant_name: &str) -> bool {
+        !self
+            .muted_variants
+            .iter()
+            .any(|(muted_category, muted_name)| {
+                muted_category == &category && muted_name == variant_name
+            })
+    }
+
+    pub fn is_subject_muted(&self, subject: &Subject) -> bool {
+        self.muted_subjects.contains(subject)
+    }
+
+    /// Actionable notifications like join requests and ownership transfers are never skipped,
+    /// they are only delivered silently during quiet hours
+    pub fn decide(&self, notification: &Notification, now: u64) -> DeliveryDecision {
+        if !notification.is_actionable {
+            if let Some(reason) = self.skip_reason(notification) {
+                return DeliveryDecision::Skip(reason);
+            }
+        }
+
+        match &self.quiet_hours {
+            Some(quiet_hours) if quiet_hours.is_quiet(now) => DeliveryDecision::DeliverSilently,
+            _ => DeliveryDecision::Deliver,
+        }
+    }
+
+    fn skip_reason(&self, notification: &Notification) -> Option<SkipReason> {
+        let notification_type = &notification.notification_type;
+        let category = notification_type.category();
+
+        if !self.is_category_enabled(category) {
+            return Some(SkipReason::CategoryMuted(category));
+        }
+
+        let variant_name = notification_type.variant_name();
+        if !self.is_variant_enabled(category, variant_name) {
+            return Some(SkipReason::VariantMuted(variant_name.to_string()));
+        }
+
+        notification_type
+            .subjects()
+            .into_iter()
+            .find(|subject| self.is_subject_muted(subject))
+            .map(SkipReason::SubjectMuted)
+    }
+}
+
+/// Decides with the preferences of the recipient whether the notification is written into their
+/// `UserNotifications` and whether it is pushed
+pub fn should_deliver(
+    notification: &Notification,
+    recipient: &ProfileWithRefs,
+) -> DeliveryDecision {
+    recipient
+        .get_notification_preferences()
+        .decide(notification, time())
+}
@@ -1,10 +1,9 @@
 use std::collections::HashMap;
 
 use candid::{CandidType, Deserialize, Principal};
-use ic_cdk::api::time;
 use serde::Serialize;
 
-use crate::impl_storable_for;
+use crate::{helpers::time_helper::time, impl_storable_for};
 
 use super::{
     invite_type::InviteType,
@@ -31,6 +30,8 @@ impl Member {
             group_id,
             Join {
                 roles,
+                rsvp: None,
+                checked_in_at: None,
                 updated_at: time(),
                 created_at: time(),
             },
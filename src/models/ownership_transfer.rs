@@ -0,0 +1,75 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::time_helper::time, CanisterResult};
+
+use super::{api_error::ApiError, subject::Subject};
+
+pub const OWNERSHIP_TRANSFER_EXPIRATION: u64 = 604800000000000; // 1 week in nanoseconds
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub struct OwnershipTransfer {
+    pub from: Principal,
+    pub to: Principal,
+    pub notification_id: Option<u64>,
+    pub created_at: u64,
+    pub expires_at: u64,
+}
+
+impl OwnershipTransfer {
+    pub fn new(from: Principal, to: Principal) -> Self {
+        Self {
+            from,
+            to,
+            notification_id: None,
+            created_at: time(),
+            expires_at: time() + OWNERSHIP_TRANSFER_EXPIRATION,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        time() > self.expires_at
+    }
+
+    pub fn set_notification_id(&mut self, notification_id: u64) {
+        self.notification_id = Some(notification_id);
+    }
+
+    pub fn remove_notification_id(&mut self) {
+        self.notification_id = None;
+    }
+
+    /// Checks if the caller is the proposed owner and the proposal did not expire
+    pub fn guard_response(&self, caller: Principal) -> CanisterResult<()> {
+        if self.is_expired() {
+            return Err(ApiError::bad_request()
+                .add_method_name("guard_response")
+                .add_message("Ownership transfer has expired"));
+        }
+
+        if caller != self.to {
+            return Err(ApiError::unauthorized()
+                .add_method_name("guard_response")
+                .add_message("Only the proposed owner can respond to the ownership transfer"));
+        }
+
+        Ok(())
+    }
+
+    pub fn to_response(&self, subject: Subject) -> OwnershipTransferResponse {
+        OwnershipTransferResponse {
+            subject,
+            from: self.from,
+            to: self.to,
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct OwnershipTransferResponse {
+    pub subject: Subject,
+    pub from: Principal,
+    pub to: Principal,
+    pub expires_at: u64,
+}
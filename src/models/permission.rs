@@ -36,6 +36,10 @@ impl Permission {
     pub fn actions(&self) -> PermissionActions {
         self.actions
     }
+
+    pub fn allows(&self, permission: &PermissionType, action: &PermissionActionType) -> bool {
+        self.name == permission.to_string() && self.actions.allows(action)
+    }
 }
 
 #[derive(Clone, CandidType, Serialize, Deserialize, Debug)]
@@ -77,9 +81,19 @@ impl PermissionActions {
     pub fn delete(&self) -> bool {
         self.delete
     }
+
+    pub fn allows(&self, action: &PermissionActionType) -> bool {
+        use PermissionActionType::*;
+        match action {
+            Write => self.write,
+            Read => self.read,
+            Edit => self.edit,
+            Delete => self.delete,
+        }
+    }
 }
 
-#[derive(Clone, CandidType, Serialize, Deserialize, Debug)]
+#[derive(Clone, CandidType, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum PermissionActionType {
     Write,
     Read,
@@ -87,6 +101,18 @@ pub enum PermissionActionType {
     Delete,
 }
 
+impl fmt::Display for PermissionActionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PermissionActionType::*;
+        match &self {
+            Write => write!(f, "write"),
+            Read => write!(f, "read"),
+            Edit => write!(f, "edit"),
+            Delete => write!(f, "delete"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, CandidType, Deserialize)]
 pub enum PermissionType {
     // Canister
@@ -96,6 +122,7 @@ pub enum PermissionType {
     Member(Option<PermissionActions>),
     Invite(Option<PermissionActions>),
     Attendee(Option<PermissionActions>),
+    Role(Option<PermissionActions>),
 
     // Frontend chat
     ChatNotification(Option<PermissionActions>),
@@ -107,6 +134,22 @@ pub enum PermissionType {
     ChatPolls(Option<PermissionActions>),
 }
 
+impl PermissionType {
+    pub fn is_chat(&self) -> bool {
+        use PermissionType::*;
+        matches!(
+            self,
+            ChatNotification(_)
+                | ChatMessage(_)
+                | ChatChannel(_)
+                | ChatEmbed(_)
+                | ChatFiles(_)
+                | ChatMention(_)
+                | ChatPolls(_)
+        )
+    }
+}
+
 impl fmt::Display for PermissionType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use PermissionType::*;
@@ -117,6 +160,7 @@ impl fmt::Display for PermissionType {
             Member(_) => write!(f, "member"),
             Invite(_) => write!(f, "invite"),
             Attendee(_) => write!(f, "attendee"),
+            Role(_) => write!(f, "role"),
             ChatNotification(_) => write!(f, "chat_notification"),
             ChatMessage(_) => write!(f, "chat_message"),
             ChatChannel(_) => write!(f, "chat_channel"),
@@ -1,10 +1,10 @@
 use std::collections::HashMap;
 
 use candid::{CandidType, Deserialize, Principal};
-use ic_cdk::api::time;
 use serde::Serialize;
 
 use crate::{
+    helpers::time_helper::time,
     impl_storable_for,
     models::{
         application_role::ApplicationRole, asset::Asset, date_range::DateRange,
@@ -1,8 +1,8 @@
 use candid::{CandidType, Deserialize, Principal};
-use ic_cdk::api::time;
 use serde::Serialize;
 
 use crate::{
+    helpers::time_helper::time,
     impl_storable_for,
     models::{
         application_role::ApplicationRole, asset::Asset, date_range::DateRange,
@@ -15,6 +15,7 @@ use crate::{
 use super::{
     document_details::DocumentDetails,
     general_structs::privacy::Privacy,
+    notification_preferences::NotificationPreferences,
     profile_structs::{
         profile_documents::ProfileDocuments, profile_metadata::ProfileMetadata,
         profile_references::ProfileReferences,
@@ -35,6 +36,7 @@ pub struct ProfileWithRefs {
     pub references: ProfileReferences,
     pub extra: Option<String>,
     pub notification_id: Option<u64>,
+    pub notification_preferences: Option<NotificationPreferences>,
     pub updated_on: u64,
     pub created_on: u64,
 }
@@ -49,6 +51,7 @@ impl From<PostProfile> for ProfileWithRefs {
             references: ProfileReferences::default(),
             extra: None,
             notification_id: None,
+            notification_preferences: None,
             updated_on: time(),
             created_on: time(),
         }
@@ -81,6 +84,7 @@ impl ProfileWithRefs {
             application_role: self.application_role,
             references: self.references,
             notification_id: self.notification_id,
+            notification_preferences: self.notification_preferences,
         }
     }
 
@@ -92,6 +96,23 @@ impl ProfileWithRefs {
         self.notification_id = None;
     }
 
+    pub fn get_notification_preferences(&self) -> NotificationPreferences {
+        self.notification_preferences.clone().unwrap_or_default()
+    }
+
+    pub fn set_notification_preferences(
+        &mut self,
+        preferences: NotificationPreferences,
+    ) -> CanisterResult<NotificationPreferences> {
+        preferences
+            .validate()
+            .map_err(|err| err.add_method_name("set_notification_preferences"))?;
+
+        self.notification_preferences = Some(preferences.clone());
+        self.updated_on = time();
+        Ok(preferences)
+    }
+
     pub fn is_starred(&self, subject: &Subject) -> bool {
         self.references.starred.contains(subject)
     }
@@ -0,0 +1,207 @@
+use candid::Principal;
+
+use super::{
+    event_with_attendees::EventWithAttendees,
+    group_with_members::GroupWithMembers,
+    notification::{
+        EventNotificationType, GroupNotificationType, Notification, NotificationType,
+        RelationNotificationType, TransactionNotificationType,
+    },
+    subject::Subject,
+    user_notifications::UserNotificationData,
+};
+
+/// The group or event the notification is about, used to look up members, admins and bans
+#[derive(Clone, Copy, Debug, Default)]
+pub enum RecipientContext<'a> {
+    #[default]
+    None,
+    /// The group with its id
+    Group(u64, &'a GroupWithMembers),
+    /// The event with its id
+    Event(u64, &'a EventWithAttendees),
+}
+
+/// Computes who receives a notification:
+/// - owner and admins for user requests, their answers and joins and leaves
+/// - the invitee or affected member for owner requests, role changes and removals
+/// - all members or attendees for reminders
+/// - the proposed owner for ownership transfer requests and the current owner for the answer
+/// - the buyer for ticket purchases and the receiver for other transactions
+///
+/// Banned principals and the blocked principals are never recipients, the sender only receives transactions.
+/// Multisig notifications and digests are addressed by their own canister and resolve to nobody.
+#[derive(Clone, Debug, Default)]
+pub struct RecipientResolver<'a> {
+    context: RecipientContext<'a>,
+    blocked: Vec<Principal>,
+}
+
+impl<'a> RecipientResolver<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn for_group(group_id: u64, group: &'a GroupWithMembers) -> Self {
+        Self {
+            context: RecipientContext::Group(group_id, group),
+            blocked: vec![],
+        }
+    }
+
+    pub fn for_event(event_id: u64, event: &'a EventWithAttendees) -> Self {
+        Self {
+            context: RecipientContext::Event(event_id, event),
+            blocked: vec![],
+        }
+    }
+
+    /// The group or event of the context, `None` without a context
+    pub fn subject(&self) -> Option<Subject> {
+        match self.context {
+            RecipientContext::None => None,
+            RecipientContext::Group(group_id, _) => Some(Subject::Group(group_id)),
+            RecipientContext::Event(event_id, _) => Some(Subject::Event(event_id)),
+        }
+    }
+
+    /// Principals that blocked the sender, for example from the relations of the sender's profile
+    pub fn with_blocked(mut self, blocked: Vec<Principal>) -> Self {
+        self.blocked = blocked;
+        self
+    }
+
+    /// The recipients with the user data to store in their `UserNotifications`
+    pub fn resolve(&self, notification: &Notification) -> Vec<(Principal, UserNotificationData)> {
+        let category = notification.notification_type.category();
+        self.recipients(notification)
+            .into_iter()
+            .map(|recipient| {
+                (
+                    recipient,
+                    UserNotificationData::new(false, false).with_category(category),
+                )
+            })
+            .collect()
+    }
+
+    /// The sorted and deduplicated recipients of the notification
+    pub fn recipients(&self, notification: &Notification) -> Vec<Principal> {
+        // a transaction is reported to its party, also when that party made the call, like the buyer of a ticket
+        let is_receipt = matches!(
+            notification.notification_type,
+            NotificationType::Transaction(_)
+        );
+
+        let mut recipients: Vec<Principal> = self
+            .candidates(&notification.notification_type)
+            .into_iter()
+            .filter(|recipient| {
+                (is_receipt || recipient != &notification.sender)
+                    && !self.blocked.contains(recipient)
+                    && !self.is_banned(*recipient)
+            })
+            .collect();
+
+        recipients.sort();
+        recipients.dedup();
+        recipients
+    }
+
+    fn candidates(&self, notification_type: &NotificationType) -> Vec<Principal> {
+        match notification_type {
+            NotificationType::Group(group) => {
+                use GroupNotificationType::*;
+                match group {
+                    JoinGroupUserRequest(_)
+                    | JoinGroupOwnerRequestAccept(_)
+                    | JoinGroupOwnerRequestDecline(_)
+                    | UserJoinGroup(_)
+                    | UserLeaveGroup(_) => self.managers(),
+                    JoinGroupUserRequestAccept(response)
+                    | JoinGroupUserRequestDecline(response)
+                    | JoinGroupOwnerRequest(response)
+                    | RemoveInviteByOwner(response) => vec![response.principal],
+                    RoleAssignByOwner(response) | RemoveMemberByOwner(response) => {
+                        vec![response.principal]
+                    }
+                    GroupReminder(_) => self.everyone(),
+                    OwnershipTransferRequest(response) => vec![response.to],
+                    OwnershipTransferAccept(response) | OwnershipTransferDecline(response) => {
+                        vec![response.from]
+                    }
+                }
+            }
+            NotificationType::Event(event) => {
+                use EventNotificationType::*;
+                match event {
+                    JoinEventUserRequest(_)
+                    | JoinEventOwnerRequestAccept(_)
+                    | JoinEventOwnerRequestDecline(_)
+                    | UserJoinEvent(_)
+                    | UserLeaveEvent(_) => self.managers(),
+                    JoinEventUserRequestAccept(response)
+                    | JoinEventUserRequestDecline(response)
+                    | JoinEventOwnerRequest(response)
+                    | RemoveInviteByOwner(response) => vec![response.principal],
+                    RoleAssignByOwner(response)
+                    | RemoveAttendeeByOwner(response)
+                    | WaitlistPromoted(response) => vec![response.principal],
+                    EventReminder(_) => self.everyone(),
+                    OwnershipTransferRequest(response) => vec![response.to],
+                    OwnershipTransferAccept(response) | OwnershipTransferDecline(response) => {
+                        vec![response.from]
+                    }
+                }
+            }
+            NotificationType::Relation(relation) => {
+                use RelationNotificationType::*;
+                match relation {
+                    FriendRequest(request) => vec![request.to],
+                    FriendRequestAccept(request) | FriendRequestDecline(request) => {
+                        vec![request.requested_by]
+                    }
+                    FriendRemove(principal) => vec![*principal],
+                    FriendRequestRemove(_) | FriendRequestReminder(_) | BlockUser(_) => vec![],
+                }
+            }
+            NotificationType::Transaction(transaction) => match transaction {
+                TransactionNotificationType::SingleTransaction(data) => vec![data.get_recipient()],
+                TransactionNotificationType::TransactionsComplete(data) => vec![data.sender],
+            },
+            NotificationType::Multisig(_) | NotificationType::Digest(_) => vec![],
+        }
+    }
+
+    fn managers(&self) -> Vec<Principal> {
+        match self.context {
+            RecipientContext::None => vec![],
+            RecipientContext::Group(_, group) => {
+                let mut managers = group.members.get_owner_and_admins();
+                managers.push(group.owner);
+                managers
+            }
+            RecipientContext::Event(_, event) => {
+                let mut managers = event.attendees.get_owner_and_admins();
+                managers.push(event.owner);
+                managers
+            }
+        }
+    }
+
+    fn everyone(&self) -> Vec<Principal> {
+        match self.context {
+            RecipientContext::None => vec![],
+            RecipientContext::Group(_, group) => group.get_members(),
+            RecipientContext::Event(_, event) => event.get_members(),
+        }
+    }
+
+    fn is_banned(&self, principal: Principal) -> bool {
+        match self.context {
+            RecipientContext::None => false,
+            RecipientContext::Group(_, group) => group.is_banned_member(principal),
+            RecipientContext::Event(_, event) => event.is_banned_member(principal),
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    helpers::time_helper::{
+        civil_from_days, days_from_civil, days_in_month, days_to_nanoseconds, split_nanoseconds,
+        weekday_from_days, NANOSECONDS_PER_SECOND,
+    },
+    CanisterResult,
+};
+
+use super::{api_error::ApiError, date_range::DateRange};
+
+// upper bound on the generated candidates so an unbounded rule can not exhaust the instruction limit
+pub const MAX_RECURRENCE_ITERATIONS: usize = 10_000;
+
+#[derive(CandidType, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub enum RecurrenceFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+#[derive(
+    CandidType, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug,
+)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// Days since monday
+    pub fn offset(&self) -> i64 {
+        *self as i64
+    }
+}
+
+/// RRULE style recurrence of an event, the first date of the event is the first occurrence.
+/// Occurrences are expanded in the timezone of the first date, they keep its local weekday and time of day across
+/// daylight saving time changes and the duration of the first date.
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct Recurrence {
+    pub frequency: RecurrenceFrequency,
+    /// Repeat every `interval` days, weeks or months
+    pub interval: u32,
+    /// Weekdays of a weekly recurrence, defaults to the weekday of the first date when empty
+    pub by_weekday: Vec<Weekday>,
+    /// The maximum amount of occurrences including the first date, exceptions are counted as well
+    pub count: Option<u32>,
+    /// Occurrences that start after this timestamp are not generated
+    pub until: Option<u64>,
+    /// Start timestamps of skipped occurrences
+    pub exceptions: Vec<u64>,
+}
+
+impl Recurrence {
+    pub fn validate(&self) -> CanisterResult<()> {
+        if self.interval == 0 {
+            return Err(ApiError::bad_request()
+                .add_method_name("validate")
+                .add_message("Recurrence interval must be at least 1")
+                .add_tag("recurrence"));
+        }
+
+        if self.count == Some(0) {
+            return Err(ApiError::bad_request()
+                .add_method_name("validate")
+                .add_message("Recurrence count must be at least 1")
+                .add_tag("recurrence"));
+        }
+
+        if !self.by_weekday.is_empty() && self.frequency != RecurrenceFrequency::Weekly {
+            return Err(ApiError::bad_request()
+                .add_method_name("validate")
+                .add_message("Weekdays are only supported for weekly recurrences")
+                .add_tag("recurrence"));
+        }
+
+        Ok(())
+    }
+
+    /// The occurrences of `first` that overlap with `range`, a `range` with an `end_date` of `0` is open-ended
+    pub fn occurrences_between(&self, first: &DateRange, range: &DateRange) -> Vec<DateRange> {
+        let mut occurrences = vec![];
+        for occurrence in self.occurrences(first) {
+            if range.is_after_end_date(occurrence.start_date()) {
+                break;
+            }
+
+            if occurrence.overlaps(range) {
+                occurrences.push(occurrence);
+            }
+        }
+        occurrences
+    }
+
+    /// The first occurrence that has not ended at `now`
+    pub fn next_occurrence(&self, first: &DateRange, now: u64) -> Option<DateRange> {
+        self.occurrences(first)
+            .find(|occurrence| occurrence.end_date() >= now)
+    }
+
+    /// All occurrences in chronological order, limited by `count`, `until` and `MAX_RECURRENCE_ITERATIONS`
+    pub fn occurrences<'a>(&'a self, first: &DateRange) -> impl Iterator<Item = DateRange> + 'a {
+        let duration = first.end_date().saturating_sub(first.start_date());
+        let timezone = first.timezone.clone();
+
+        self.starts(first)
+            .take(self.count.map_or(usize::MAX, |count| count as usize))
+            .take_while(|start| self.until.is_none_or(|until| *start <= until))
+            .filter(|start| !self.exceptions.contains(start))
+            .map(move |start| DateRange {
+                start_date: start,
+                end_date: start.saturating_add(duration),
+                timezone: timezone.clone(),
+            })
+    }
+
+    fn starts(&self, first: &DateRange) -> impl Iterator<Item = u64> + '_ {
+        let interval = self.interval.max(1) as i64;
+        let first_start = first.start_date();
+        let offset = first.utc_offset_seconds() as i128 * NANOSECONDS_PER_SECOND as i128;
+        let first_local = (first_start as i128 + offset).clamp(0, u64::MAX as i128) as u64;
+        let (first_day, time_of_day) = split_nanoseconds(first_local);
+        let first = first.clone();
+
+        let weekdays: Vec<i64> = match self.by_weekday.is_empty() {
+            true => vec![weekday_from_days(first_day) as i64],
+            false => {
+                let mut weekdays: Vec<i64> = self.by_weekday.iter().map(Weekday::offset).collect();
+                weekdays.sort();
+                weekdays.dedup();
+                weekdays
+            }
+        };
+
+        let (first_year, first_month, first_month_day) = civil_from_days(first_day);
+        let first_monday = first_day - weekday_from_days(first_day) as i64;
+
+        // the first date is always an occurrence, even when it does not match the rule
+        std::iter::once(first_start).chain(
+            (0..MAX_RECURRENCE_ITERATIONS as i64)
+                .flat_map(move |step| -> Vec<i64> {
+                    match self.frequency {
+                        RecurrenceFrequency::Daily => vec![first_day + step * interval],
+                        RecurrenceFrequency::Weekly => {
+                            let monday = first_monday + step * interval * 7;
+                            weekdays.iter().map(|offset| monday + offset).collect()
+                        }
+                        // months without the day of the first date are skipped
+                        RecurrenceFrequency::Monthly => {
+                            let months = first_month as i64 - 1 + step * interval;
+                            let year = first_year + months.div_euclid(12);
+                            let month = months.rem_euclid(12) as u32 + 1;
+                            match first_month_day <= days_in_month(year, month) {
+                                true => vec![days_from_civil(year, month, first_month_day)],
+                                false => vec![],
+                            }
+                        }
+                    }
+                })
+                .filter(move |day| *day > first_day)
+                .map(move |day| days_to_nanoseconds(day).saturating_add(time_of_day))
+                // stops once the timestamps saturate beyond the range of `u64` nanoseconds
+                .take_while(|local| *local != u64::MAX)
+                .map(move |local| first.local_to_utc(local)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::helpers::time_helper::parse_iso8601;
+
+    use super::*;
+
+    fn at(value: &str) -> u64 {
+        parse_iso8601(value).expect("valid date")
+    }
+
+    fn weekly(by_weekday: Vec<Weekday>) -> Recurrence {
+        Recurrence {
+            frequency: RecurrenceFrequency::Weekly,
+            interval: 1,
+            by_weekday,
+            count: Some(3),
+            until: None,
+            exceptions: vec![],
+        }
+    }
+
+    fn starts(recurrence: &Recurrence, first: &DateRange) -> Vec<u64> {
+        recurrence
+            .occurrences(first)
+            .map(|occurrence| occurrence.start_date())
+            .collect()
+    }
+
+    #[test]
+    fn weekdays_are_expanded_in_the_local_timezone() {
+        // monday 08:00 in Tokyo is sunday 23:00 UTC
+        let start = at("2024-01-07T23:00:00Z");
+        let first =
+            DateRange::new(start, start + 3600 * NANOSECONDS_PER_SECOND).with_timezone("+09:00");
+        let recurrence = weekly(vec![Weekday::Monday, Weekday::Wednesday]);
+
+        assert_eq!(
+            starts(&recurrence, &first),
+            vec![
+                start,
+                at("2024-01-09T23:00:00Z"),
+                at("2024-01-14T23:00:00Z")
+            ]
+        );
+    }
+
+    #[test]
+    fn occurrences_keep_the_local_time_across_daylight_saving_time() {
+        // 10:00 in Amsterdam, daylight saving time starts on sunday 2024-03-31
+        let start = at("2024-03-25T09:00:00Z");
+        let first = DateRange::new(start, start + 3600 * NANOSECONDS_PER_SECOND)
+            .with_timezone("Europe/Amsterdam");
+
+        let occurrences: Vec<DateRange> = weekly(vec![]).occurrences(&first).collect();
+
+        assert_eq!(occurrences[1].start_date(), at("2024-04-01T08:00:00Z"));
+        assert_eq!(occurrences[1].end_date(), at("2024-04-01T09:00:00Z"));
+        assert_eq!(occurrences[2].start_date(), at("2024-04-08T08:00:00Z"));
+    }
+}
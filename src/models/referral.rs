@@ -1,8 +1,7 @@
 use candid::CandidType;
-use ic_cdk::api::time;
 use serde::{Deserialize, Serialize};
 
-use crate::impl_storable_for;
+use crate::{helpers::time_helper::time, impl_storable_for};
 
 impl_storable_for!(Referral);
 
@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, HashMap};
+
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::{helpers::time_helper::hours_to_nanoseconds, impl_storable_for};
+
+use super::{
+    date_range::DateRange,
+    event_with_attendees::EventWithAttendees,
+    member::RsvpStatus,
+    notification::{EventNotificationType, NotificationType},
+};
+
+impl_storable_for!(ReminderLog);
+
+/// Lead times in nanoseconds that are used for users without their own lead times, 24 hours and 1 hour
+pub fn default_reminder_lead_times() -> Vec<u64> {
+    vec![hours_to_nanoseconds(24), hours_to_nanoseconds(1)]
+}
+
+/// Per user lead times in nanoseconds before the start of an occurrence
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct ReminderLeadTimes {
+    pub default: Vec<u64>,
+    pub per_user: HashMap<Principal, Vec<u64>>,
+}
+
+impl Default for ReminderLeadTimes {
+    fn default() -> Self {
+        Self {
+            default: default_reminder_lead_times(),
+            per_user: HashMap::new(),
+        }
+    }
+}
+
+impl ReminderLeadTimes {
+    pub fn get(&self, principal: &Principal) -> &[u64] {
+        self.per_user
+            .get(principal)
+            .map_or(self.default.as_slice(), |lead_times| lead_times.as_slice())
+    }
+
+    pub fn set(&mut self, principal: Principal, lead_times: Vec<u64>) {
+        self.per_user.insert(principal, lead_times);
+    }
+
+    pub fn max(&self) -> u64 {
+        self.default
+            .iter()
+            .chain(self.per_user.values().flatten())
+            .copied()
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+/// Identifies the reminders of a single occurrence for a single attendee
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ReminderKey {
+    pub occurrence_start: u64,
+    pub event_id: u64,
+    pub principal: Principal,
+}
+
+#[derive(Clone, Debug, CandidType, Serialize, Deserialize)]
+pub struct ReminderJob {
+    pub key: ReminderKey,
+    pub lead_time: u64,
+    pub group_id: Option<u64>,
+    pub occurrence: DateRange,
+    pub due_at: u64,
+}
+
+impl ReminderJob {
+    pub fn notification_type(&self) -> NotificationType {
+        NotificationType::Event(EventNotificationType::EventReminder(self.key.event_id))
+    }
+}
+
+/// The smallest lead time that was reminded per occurrence and attendee. The log is meant to be kept in
+/// stable memory so a timer that is restarted after an upgrade does not send the same reminders again.
+#[derive(Clone, Debug, Default, CandidType, Serialize, Deserialize)]
+pub struct ReminderLog {
+    sent: BTreeMap<ReminderKey, u64>,
+}
+
+impl ReminderLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sent.is_empty()
+    }
+
+    /// A lead time counts as sent when the same or a smaller lead time was reminded
+    pub fn is_sent(&self, key: &ReminderKey, lead_time: u64) -> bool {
+        self.sent
+            .get(key)
+            .is_some_and(|sent_lead_time| *sent_lead_time <= lead_time)
+    }
+
+    /// Returns `false` when the reminder was already marked as sent
+    pub fn mark_sent(&mut self, job: &ReminderJob) -> bool {
+        if self.is_sent(&job.key, job.lead_time) {
+            return false;
+        }
+
+        self.sent.insert(job.key.clone(), job.lead_time);
+        true
+    }
+
+    /// Drops the keys of occurrences that started before `before`, those are never planned again
+    pub fn prune(&mut self, before: u64) {
+        self.sent = self.sent.split_off(&ReminderKey {
+            occurrence_start: before,
+            event_id: 0,
+            principal: Principal::management_canister(),
+        });
+    }
+}
+
+/// Plans the reminders that are due at `now` and not in the log yet, ordered by due time.
+///
+/// Meant to run from an interval timer: send the notifications for the returned jobs, record them with
+/// `ReminderLog::mark_sent` and `prune` the log with `now`. Reminders are only planned for occurrences that
+/// did not start yet, so a timer that was down for a while skips reminders whose occurrence already started
+/// and only sends the closest of the lead times it missed.
+/// Canceled and deleted events and attendees that declined are skipped.
+pub fn plan_reminders(
+    events: &[(u64, EventWithAttendees)],
+    lead_times: &ReminderLeadTimes,
+    log: &ReminderLog,
+    now: u64,
+) -> Vec<ReminderJob> {
+    let window = DateRange::new(now, now.saturating_add(lead_times.max()));
+
+    let mut jobs: Vec<ReminderJob> = vec![];
+    for (event_id, event) in events {
+        if event.is_canceled.is_some() || event.is_deleted {
+            continue;
+        }
+
+        for occurrence in event.occurrences_between(&window) {
+            if occurrence.start_date() <= now {
+                continue;
+            }
+
+            for (principal, join) in &event.attendees.members {
+                if join.get_rsvp() == RsvpStatus::Declined {
+                    continue;
+                }
+
+                let key = ReminderKey {
+                    occurrence_start: occurrence.start_date(),
+                    event_id: *event_id,
+                    principal: *principal,
+                };
+
+                // when several lead times are due only the closest one is reminded
+                let Some(lead_time) = lead_times
+                    .get(principal)
+                    .iter()
+                    .filter(|lead_time| occurrence.start_date().saturating_sub(**lead_time) <= now)
+                    .min()
+                    .copied()
+                else {
+                    continue;
+                };
+
+                if log.is_sent(&key, lead_time) {
+                    continue;
+                }
+
+                jobs.push(ReminderJob {
+                    key,
+                    lead_time,
+                    group_id: event.group_id,
+                    occurrence: occurrence.clone(),
+                    due_at: occurrence.start_date().saturating_sub(lead_time),
+                });
+            }
+        }
+    }
+
+    jobs.sort_by_key(|job| job.due_at);
+    jobs
+}
@@ -1,9 +1,10 @@
 use candid::{CandidType, Deserialize, Principal};
 
-use ic_cdk::{api::time, caller};
+use ic_cdk::caller;
 use serde::Serialize;
 
 use crate::{
+    helpers::time_helper::time,
     impl_storable_for,
     models::{date_range::DateRange, sort_direction::SortDirection},
     Filter, Sorter,
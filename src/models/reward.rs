@@ -1,10 +1,9 @@
 use std::fmt;
 
 use candid::{CandidType, Decode, Encode, Principal};
-use ic_cdk::api::time;
 use serde::{Deserialize, Serialize};
 
-use crate::impl_storable_for;
+use crate::{helpers::time_helper::time, impl_storable_for};
 
 impl_storable_for!(RewardableActivity);
 
@@ -1,6 +1,11 @@
 use candid::{CandidType, Deserialize};
 use serde::Serialize;
 
+use crate::misc::role_misc::{
+    ADMIN_ROLE, ADMIN_ROLE_RANK, CUSTOM_ROLE_RANK_OFFSET, MEMBER_ROLE, MEMBER_ROLE_RANK,
+    MODERATOR_ROLE, MODERATOR_ROLE_RANK, OWNER_ROLE, OWNER_ROLE_RANK,
+};
+
 use super::permission::{Permission, PostPermission};
 
 #[derive(Clone, CandidType, Serialize, Deserialize, Debug, Default)]
@@ -52,6 +57,31 @@ impl Role {
     pub fn permissions(&self) -> Vec<Permission> {
         self.permissions.clone()
     }
+
+    pub fn is_default_role(&self) -> bool {
+        [OWNER_ROLE, ADMIN_ROLE, MODERATOR_ROLE, MEMBER_ROLE].contains(&self.name.as_str())
+    }
+
+    /// The position of the role in the hierarchy, a lower rank means more authority.
+    /// The default roles have a fixed rank, custom roles are ranked by their `index`
+    /// between the moderator and the member role.
+    pub fn rank(&self) -> u64 {
+        match self.name.as_str() {
+            name if name == OWNER_ROLE => OWNER_ROLE_RANK,
+            name if name == ADMIN_ROLE => ADMIN_ROLE_RANK,
+            name if name == MODERATOR_ROLE => MODERATOR_ROLE_RANK,
+            name if name == MEMBER_ROLE => MEMBER_ROLE_RANK,
+            _ => self
+                .index
+                .map(|index| CUSTOM_ROLE_RANK_OFFSET.saturating_add(index))
+                .unwrap_or(MEMBER_ROLE_RANK)
+                .min(MEMBER_ROLE_RANK - 1),
+        }
+    }
+
+    pub fn is_above(&self, other: &Role) -> bool {
+        self.rank() < other.rank()
+    }
 }
 
 #[derive(Clone, CandidType, Serialize, Deserialize, Debug)]
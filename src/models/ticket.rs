@@ -0,0 +1,231 @@
+use candid::{CandidType, Nat, Principal};
+use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue;
+use serde::{Deserialize, Serialize};
+
+use crate::CanisterResult;
+
+use super::{
+    api_error::ApiError,
+    date_range::DateRange,
+    notification::{NotificationType, TransactionNotificationType},
+    transaction_data::TransactionData,
+};
+
+pub const TICKET_PURCHASE_KIND: &str = "ticket_purchase";
+pub const TICKET_REFUND_KIND: &str = "ticket_refund";
+
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct TicketType {
+    pub id: u64,
+    pub name: String,
+    pub description: String,
+    /// The ICRC-1 ledger of the token the ticket is paid with
+    pub ledger: Principal,
+    /// Price in the smallest unit of the token
+    pub price: u64,
+    pub quantity: u64,
+    pub sold: u64,
+    /// Tickets can only be bought within the window, an `end_date` of `0` keeps the sale open
+    pub sale_window: DateRange,
+}
+
+impl TicketType {
+    pub fn new(id: u64, ticket_type: PostTicketType) -> Self {
+        Self {
+            id,
+            name: ticket_type.name,
+            description: ticket_type.description,
+            ledger: ticket_type.ledger,
+            price: ticket_type.price,
+            quantity: ticket_type.quantity,
+            sold: 0,
+            sale_window: ticket_type.sale_window,
+        }
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.quantity.saturating_sub(self.sold)
+    }
+
+    pub fn is_sold_out(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    pub fn is_on_sale(&self, now: u64) -> bool {
+        self.sale_window.is_within(now)
+    }
+}
+
+#[derive(CandidType, Clone, Serialize, Deserialize, Debug)]
+pub struct PostTicketType {
+    pub name: String,
+    pub description: String,
+    pub ledger: Principal,
+    pub price: u64,
+    pub quantity: u64,
+    pub sale_window: DateRange,
+}
+
+impl PostTicketType {
+    pub fn validate(&self) -> CanisterResult<()> {
+        if self.name.trim().is_empty() {
+            return Err(ApiError::bad_request()
+                .add_method_name("validate")
+                .add_message("Ticket type name is required")
+                .add_tag("ticket"));
+        }
+
+        // free events are joined without a ticket
+        if self.price == 0 {
+            return Err(ApiError::bad_request()
+                .add_method_name("validate")
+                .add_message("Ticket price must be above 0")
+                .add_tag("ticket"));
+        }
+
+        if self.quantity == 0 {
+            return Err(ApiError::bad_request()
+                .add_method_name("validate")
+                .add_message("Ticket quantity must be at least 1")
+                .add_tag("ticket"));
+        }
+
+        if !self.sale_window.is_open_ended()
+            && self.sale_window.end_date() < self.sale_window.start_date()
+        {
+            return Err(ApiError::bad_request()
+                .add_method_name("validate")
+                .add_message("Sale window ends before it starts")
+                .add_tag("ticket"));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(CandidType, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub enum TicketStatus {
+    Valid,
+    /// The event was canceled and the price still has to be sent back
+    RefundPending,
+    Refunded,
+}
+
+#[derive(CandidType, Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct Ticket {
+    pub id: u64,
+    pub ticket_type_id: u64,
+    pub owner: Principal,
+    pub ledger: Principal,
+    pub price: u64,
+    /// Block height of the payment, a block can only pay for a single ticket
+    pub blockheight: u64,
+    pub status: TicketStatus,
+    pub purchased_at: u64,
+    pub refunded_at: Option<u64>,
+    pub refund_blockheight: Option<u64>,
+}
+
+impl Ticket {
+    pub fn is_valid(&self) -> bool {
+        self.status == TicketStatus::Valid
+    }
+
+    pub fn is_refund_pending(&self) -> bool {
+        self.status == TicketStatus::RefundPending
+    }
+
+    /// The payment from the owner to the `treasury` principal
+    pub fn purchase_transaction(
+        &self,
+        event_id: u64,
+        treasury: Principal,
+        fee: Nat,
+    ) -> TransactionData {
+        self.transaction_data(
+            TICKET_PURCHASE_KIND,
+            event_id,
+            self.owner,
+            treasury,
+            self.blockheight,
+            fee,
+        )
+    }
+
+    /// The refund from the `treasury` principal to the owner, `None` when the ticket is not refunded
+    pub fn refund_transaction(
+        &self,
+        event_id: u64,
+        treasury: Principal,
+        fee: Nat,
+    ) -> Option<TransactionData> {
+        self.refund_blockheight.map(|blockheight| {
+            self.transaction_data(
+                TICKET_REFUND_KIND,
+                event_id,
+                treasury,
+                self.owner,
+                blockheight,
+                fee,
+            )
+        })
+    }
+
+    pub fn purchase_notification(
+        &self,
+        event_id: u64,
+        treasury: Principal,
+        fee: Nat,
+    ) -> NotificationType {
+        NotificationType::Transaction(TransactionNotificationType::SingleTransaction(
+            self.purchase_transaction(event_id, treasury, fee),
+        ))
+    }
+
+    pub fn refund_notification(
+        &self,
+        event_id: u64,
+        treasury: Principal,
+        fee: Nat,
+    ) -> Option<NotificationType> {
+        self.refund_transaction(event_id, treasury, fee)
+            .map(|data| {
+                NotificationType::Transaction(TransactionNotificationType::SingleTransaction(data))
+            })
+    }
+
+    fn transaction_data(
+        &self,
+        kind: &str,
+        event_id: u64,
+        sender: Principal,
+        receiver: Principal,
+        blockheight: u64,
+        fee: Nat,
+    ) -> TransactionData {
+        TransactionData {
+            sender,
+            receiver,
+            canister: self.ledger,
+            memo: None,
+            fee,
+            block_height: Nat::from(blockheight),
+            amount: Nat::from(self.price),
+            metadata: vec![
+                ("kind".to_string(), MetadataValue::Text(kind.to_string())),
+                (
+                    "event_id".to_string(),
+                    MetadataValue::Nat(Nat::from(event_id)),
+                ),
+                (
+                    "ticket_id".to_string(),
+                    MetadataValue::Nat(Nat::from(self.id)),
+                ),
+                (
+                    "ticket_type_id".to_string(),
+                    MetadataValue::Nat(Nat::from(self.ticket_type_id)),
+                ),
+            ],
+        }
+    }
+}
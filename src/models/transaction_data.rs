@@ -2,6 +2,8 @@ use candid::{CandidType, Nat, Principal};
 use icrc_ledger_types::{icrc::generic_metadata_value::MetadataValue, icrc1::transfer::Memo};
 use serde::{Deserialize, Serialize};
 
+use super::ticket::TICKET_PURCHASE_KIND;
+
 // These structs is used on the transaction_handler cansister
 // changes to these structs should be reflected on the transaction_handler canister
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
@@ -16,6 +18,24 @@ pub struct TransactionData {
     pub metadata: Vec<(String, MetadataValue)>,
 }
 
+impl TransactionData {
+    /// The `kind` metadata, like `ticket_purchase`
+    pub fn get_kind(&self) -> Option<&str> {
+        self.metadata.iter().find_map(|(key, value)| match value {
+            MetadataValue::Text(kind) if key == "kind" => Some(kind.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The principal the transaction is reported to, the buyer of a ticket and the receiver otherwise
+    pub fn get_recipient(&self) -> Principal {
+        match self.get_kind() {
+            Some(TICKET_PURCHASE_KIND) => self.sender,
+            _ => self.receiver,
+        }
+    }
+}
+
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
 pub struct TransactionCompleteData {
     pub sender: Principal,
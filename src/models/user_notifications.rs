@@ -1,21 +1,41 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 
 use crate::impl_storable_for;
 
+use super::{notification::NotificationCategory, paged_response::PagedResponse};
+
 impl_storable_for!(UserNotifications);
 
+/// The amount of notifications kept per user by `UserNotifications::apply_retention`
+pub const DEFAULT_INBOX_RETENTION: usize = 1_000;
+
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
 pub struct UserNotificationData {
     is_read: bool,
     is_sender: bool,
+    // entries that were stored before these fields existed have no category and are never archived or pinned
+    category: Option<NotificationCategory>,
+    is_archived: Option<bool>,
+    is_pinned: Option<bool>,
 }
 
 impl UserNotificationData {
     pub fn new(is_read: bool, is_sender: bool) -> Self {
-        Self { is_read, is_sender }
+        Self {
+            is_read,
+            is_sender,
+            category: None,
+            is_archived: None,
+            is_pinned: None,
+        }
+    }
+
+    pub fn with_category(mut self, category: NotificationCategory) -> Self {
+        self.category = Some(category);
+        self
     }
 
     pub fn mark_as_read(&mut self, is_read: bool) {
@@ -26,6 +46,14 @@ impl UserNotificationData {
         self.is_sender = is_sender;
     }
 
+    pub fn set_archived(&mut self, is_archived: bool) {
+        self.is_archived = Some(is_archived);
+    }
+
+    pub fn set_pinned(&mut self, is_pinned: bool) {
+        self.is_pinned = Some(is_pinned);
+    }
+
     pub fn is_read(&self) -> bool {
         self.is_read
     }
@@ -33,10 +61,49 @@ impl UserNotificationData {
     pub fn is_sender(&self) -> bool {
         self.is_sender
     }
+
+    pub fn category(&self) -> Option<NotificationCategory> {
+        self.category
+    }
+
+    pub fn is_archived(&self) -> bool {
+        self.is_archived.unwrap_or_default()
+    }
+
+    pub fn is_pinned(&self) -> bool {
+        self.is_pinned.unwrap_or_default()
+    }
+}
+
+/// Which entries of the inbox are returned, archived entries are hidden unless `archived` is set
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default)]
+pub struct InboxQuery {
+    pub category: Option<NotificationCategory>,
+    pub unread_only: bool,
+    pub pinned_only: bool,
+    pub archived: bool,
 }
 
+impl InboxQuery {
+    pub fn matches(&self, data: &UserNotificationData) -> bool {
+        self.category
+            .is_none_or(|category| data.category() == Some(category))
+            && (!self.unread_only || !data.is_read())
+            && (!self.pinned_only || data.is_pinned())
+            && data.is_archived() == self.archived
+    }
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default)]
+pub struct UnreadCounts {
+    /// Includes the entries without a category
+    pub total: u64,
+    pub per_category: Vec<(NotificationCategory, u64)>,
+}
+
+/// The inbox of a user keyed by notification id, ids are handed out in order so a higher id is more recent
 #[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
-pub struct UserNotifications(HashMap<u64, UserNotificationData>);
+pub struct UserNotifications(BTreeMap<u64, UserNotificationData>);
 
 impl Default for UserNotifications {
     fn default() -> Self {
@@ -46,13 +113,20 @@ impl Default for UserNotifications {
 
 impl UserNotifications {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self(BTreeMap::new())
     }
 
-    pub fn add(&mut self, id: u64, is_read: bool, is_sender: bool) {
-        self.0
-            .entry(id)
-            .or_insert_with(|| UserNotificationData::new(is_read, is_sender));
+    /// Adds the entry with the category of the notification when the id is not in the inbox yet
+    pub fn add(&mut self, id: u64, is_read: bool, is_sender: bool, category: NotificationCategory) {
+        self.insert(
+            id,
+            UserNotificationData::new(is_read, is_sender).with_category(category),
+        );
+    }
+
+    /// Adds the entry when the id is not in the inbox yet
+    pub fn insert(&mut self, id: u64, data: UserNotificationData) {
+        self.0.entry(id).or_insert(data);
     }
 
     pub fn remove(&mut self, id: &u64) {
@@ -60,30 +134,121 @@ impl UserNotifications {
     }
 
     pub fn mark_as_read(&mut self, id: &u64, is_read: bool) {
-        if let Some(data) = self.clone().0.get_mut(id) {
+        if let Some(data) = self.0.get_mut(id) {
             data.mark_as_read(is_read);
-            self.0.insert(*id, data.clone());
         }
     }
 
     pub fn mark_as_read_many(&mut self, ids: Vec<u64>, is_read: bool) {
         for id in ids {
-            if let Some(data) = self.clone().0.get_mut(&id) {
-                data.mark_as_read(is_read);
-                self.0.insert(id, data.clone());
+            self.mark_as_read(&id, is_read);
+        }
+    }
+
+    /// Marks the unread entries of the category as read, `None` marks every entry, and returns the changed ids
+    pub fn mark_all_read(&mut self, category: Option<NotificationCategory>) -> Vec<u64> {
+        let mut ids = vec![];
+        for (id, data) in self.0.iter_mut() {
+            if !data.is_read() && category.is_none_or(|category| data.category() == Some(category))
+            {
+                data.mark_as_read(true);
+                ids.push(*id);
             }
         }
+        ids
+    }
+
+    pub fn set_archived(&mut self, id: &u64, is_archived: bool) -> bool {
+        self.0
+            .get_mut(id)
+            .map(|data| data.set_archived(is_archived))
+            .is_some()
+    }
+
+    pub fn set_pinned(&mut self, id: &u64, is_pinned: bool) -> bool {
+        self.0
+            .get_mut(id)
+            .map(|data| data.set_pinned(is_pinned))
+            .is_some()
     }
 
     pub fn get_unread_ids(&self) -> Vec<u64> {
         self.0
-            .clone()
-            .into_iter()
+            .iter()
             .filter(|(_, data)| !data.is_read())
-            .map(|(id, _)| id)
+            .map(|(id, _)| *id)
             .collect()
     }
 
+    pub fn unread_counts(&self) -> UnreadCounts {
+        let mut per_category: BTreeMap<NotificationCategory, u64> = BTreeMap::new();
+        let mut total = 0;
+        for data in self.0.values().filter(|data| !data.is_read()) {
+            total += 1;
+            if let Some(category) = data.category() {
+                *per_category.entry(category).or_default() += 1;
+            }
+        }
+
+        UnreadCounts {
+            total,
+            per_category: per_category.into_iter().collect(),
+        }
+    }
+
+    /// A page of the matching entries, most recent first. Only the entries of the page are cloned.
+    pub fn page(
+        &self,
+        query: &InboxQuery,
+        page: usize,
+        limit: usize,
+    ) -> PagedResponse<(u64, UserNotificationData)> {
+        let total = self.0.values().filter(|data| query.matches(data)).count();
+        let limit = limit.max(1);
+        let number_of_pages = total.div_ceil(limit);
+        let page = page.clamp(1, number_of_pages.max(1));
+
+        let data = self
+            .0
+            .iter()
+            .rev()
+            .filter(|(_, data)| query.matches(data))
+            .skip((page - 1) * limit)
+            .take(limit)
+            .map(|(id, data)| (*id, data.clone()))
+            .collect();
+
+        PagedResponse {
+            page,
+            limit,
+            total,
+            number_of_pages,
+            data,
+        }
+    }
+
+    /// Drops the oldest read entries that are not pinned until at most `max` entries are left, unread and
+    /// pinned entries are always kept. Returns the removed ids.
+    pub fn apply_retention(&mut self, max: usize) -> Vec<u64> {
+        let excess = self.0.len().saturating_sub(max);
+        if excess == 0 {
+            return vec![];
+        }
+
+        let ids: Vec<u64> = self
+            .0
+            .iter()
+            .filter(|(_, data)| data.is_read() && !data.is_pinned())
+            .take(excess)
+            .map(|(id, _)| *id)
+            .collect();
+
+        for id in &ids {
+            self.0.remove(id);
+        }
+        ids
+    }
+
     pub fn remove_many(&mut self, ids: Vec<u64>) {
         for id in ids {
             self.0.remove(&id);
@@ -111,11 +276,14 @@ impl UserNotifications {
     }
 
     pub fn to_vec(&self) -> Vec<(u64, UserNotificationData)> {
-        self.0.clone().into_iter().collect()
+        self.0
+            .iter()
+            .map(|(id, data)| (*id, data.clone()))
+            .collect()
     }
 
     pub fn ids(&self) -> Vec<u64> {
-        self.0.clone().into_keys().collect()
+        self.0.keys().copied().collect()
     }
 }
 
@@ -0,0 +1,33 @@
+use crate::{
+    history_event::{HistoryEvent, HistoryEventFilter, HistoryEventSort},
+    StaticCellStorageRef, StorageClient, StorageClientInsertable,
+};
+use candid::Principal;
+
+#[derive(Clone)]
+pub struct HistoryEventStorageClient {
+    canister: StaticCellStorageRef<Principal>,
+}
+
+impl HistoryEventStorageClient {
+    pub fn new(canister: StaticCellStorageRef<Principal>) -> Self {
+        Self { canister }
+    }
+}
+
+impl StorageClient<u64, HistoryEvent, HistoryEventFilter, HistoryEventSort>
+    for HistoryEventStorageClient
+{
+    fn name(&self) -> String {
+        "history_event".to_string()
+    }
+
+    fn storage_canister_id(&self) -> StaticCellStorageRef<Principal> {
+        self.canister
+    }
+}
+
+impl StorageClientInsertable<HistoryEvent, HistoryEventFilter, HistoryEventSort>
+    for HistoryEventStorageClient
+{
+}
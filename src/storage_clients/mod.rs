@@ -1,10 +1,18 @@
 use candid::Principal;
 use group::GroupStorageClient;
+use history_event::HistoryEventStorageClient;
 
 use crate::StaticCellStorageRef;
 
 mod group;
+mod history_event;
 
 pub fn groups(canister_storage: StaticCellStorageRef<Principal>) -> GroupStorageClient {
     GroupStorageClient::new(canister_storage)
 }
+
+pub fn history_events(
+    canister_storage: StaticCellStorageRef<Principal>,
+) -> HistoryEventStorageClient {
+    HistoryEventStorageClient::new(canister_storage)
+}
@@ -48,6 +48,17 @@ pub trait StorageQueryable<Key: 'static + Storable + Ord + Clone, Value: 'static
         })
     }
 
+    /// Like `filter` but only visits the given keys, for example the candidates of a secondary index
+    fn filter_keys<F>(&self, keys: Vec<Key>, filter: F) -> Vec<(Key, Value)>
+    where
+        F: Fn(&Key, &Value) -> bool,
+    {
+        self.get_many(keys)
+            .into_iter()
+            .filter(|(id, value)| filter(id, value))
+            .collect()
+    }
+
     fn get_all(&self) -> Vec<(Key, Value)> {
         self.storage().with(|data| data.borrow().iter().collect())
     }